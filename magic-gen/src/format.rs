@@ -0,0 +1,104 @@
+use std::io::{self, Write};
+
+use types::Square;
+use xq::{
+    binary::{self, TableKind},
+    generate::{format_hex, MagicEntryGen, TableGen, TablesGen},
+    lookup::MagicTable,
+};
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Format {
+    Json,
+    Rust,
+    Binary,
+}
+
+pub struct PieceTable {
+    pub name: String,
+    pub magics: Vec<MagicEntryGen>,
+    pub table: MagicTable,
+}
+
+pub fn write_json(out: &mut dyn Write, tables: &[PieceTable], attacks: bool) -> io::Result<()> {
+    let mut output = TablesGen::new();
+    for piece in tables {
+        output.insert(
+            format!("{}_magic_table", piece.name),
+            TableGen::Magics(piece.magics.clone()),
+        );
+        if attacks {
+            let attacks = piece.table.attacks().iter().map(|a| format_hex(a.0));
+            output.insert(
+                format!("{}_attack_table", piece.name),
+                TableGen::Attacks(attacks.collect()),
+            );
+        }
+    }
+    serde_json::to_writer(&mut *out, &output)?;
+    writeln!(out)
+}
+
+// Writes `const` magic entries, and `static` attack arrays if requested,
+// meant to be `include!`d where `Hashing`, `MagicEntry` and `BitBoard` are in scope.
+pub fn write_rust(out: &mut dyn Write, tables: &[PieceTable], attacks: bool) -> io::Result<()> {
+    writeln!(out, "// Generated by magic-gen, do not edit.")?;
+    for piece in tables {
+        let name = piece.name.to_uppercase();
+        writeln!(out)?;
+        writeln!(
+            out,
+            "pub const {}_HASHING: Hashing = Hashing::{:?};",
+            name,
+            piece.table.hashing()
+        )?;
+        writeln!(
+            out,
+            "pub const {}_MAGICS: [MagicEntry; {}] = [",
+            name,
+            Square::NUM
+        )?;
+        for (square, entry) in Square::ALL.iter().zip(piece.table.entries()) {
+            writeln!(
+                out,
+                "    MagicEntry {{ mask: BitBoard({}), magic: {}, shift: {}, offset: {} }}, // {:?}",
+                format_hex(entry.mask.0),
+                format_hex(entry.magic),
+                entry.shift,
+                entry.offset,
+                square
+            )?;
+        }
+        writeln!(out, "];")?;
+        if attacks {
+            let table = piece.table.attacks();
+            writeln!(out)?;
+            writeln!(
+                out,
+                "pub static {}_ATTACKS: [BitBoard; {}] = [",
+                name,
+                table.len()
+            )?;
+            for attack in table {
+                writeln!(out, "    BitBoard({}),", format_hex(attack.0))?;
+            }
+            writeln!(out, "];")?;
+        }
+    }
+    Ok(())
+}
+
+// Writes one binary section per piece; attack tables are always included.
+pub fn write_binary(out: &mut dyn Write, tables: &[PieceTable]) -> io::Result<()> {
+    let mut data = Vec::new();
+    for piece in tables {
+        let kind = TableKind::from_name(&piece.name).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("no binary table kind for {}", piece.name),
+            )
+        })?;
+        binary::write_table(&mut data, kind, &piece.table);
+    }
+    out.write_all(&data)
+}
@@ -1,62 +1,165 @@
-use clap::Parser;
-use generate::MagicEntryGen;
+mod format;
+mod verify;
+
+use clap::{Parser, Subcommand};
+use format::{Format, PieceTable};
 use std::{
     collections::HashMap,
-    sync::{mpsc, Arc, Mutex},
+    fs::File,
+    io::{self, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    time::{Duration, Instant},
 };
-use types::Square;
+use types::{BitBoard, FenError, Position, Square};
 use xq::{
-    generate::{find_magic, ChessMove},
+    binary::{BinaryError, MAGIC},
+    generate::{
+        concat_tables, find_shared_magic, find_shared_magic_before, min_index_bits, pack_tables,
+        shared_mask, try_make_sparse_shared_table, ChessMove, Hashing, MagicEntry, MagicEntryGen,
+        TablesGen,
+    },
+    lookup::{LoadError, Lookup, MagicTable},
     rng::Rng,
     *,
 };
 
 struct FindMagicsWorker {
-    rng: Arc<Mutex<Rng>>,
+    rng: Rng,
+    pool: rayon::ThreadPool,
+    dense: Option<Duration>,
+    packed: bool,
+    hashing: Hashing,
 }
 
 impl FindMagicsWorker {
-    fn new() -> Self {
-        FindMagicsWorker {
-            rng: Arc::new(Mutex::new(Rng::default())),
+    // Uses `jobs` threads, or rayon's default of one per core.
+    fn new(
+        jobs: Option<usize>,
+        seed: u128,
+        dense: Option<Duration>,
+        packed: bool,
+        hashing: Hashing,
+    ) -> Result<Self, Error> {
+        let mut builder = rayon::ThreadPoolBuilder::new();
+        if let Some(thread_count) = jobs {
+            builder = builder.num_threads(thread_count);
         }
+        Ok(FindMagicsWorker {
+            rng: Rng::new(seed),
+            pool: builder.build()?,
+            dense,
+            packed,
+            hashing,
+        })
     }
 
+    // Several pieces get one magic per square valid for all of them,
+    // with their attacks interleaved.
     fn find_and_print_all_magics(
         &mut self,
-        slider: Arc<dyn ChessMove + Send + Sync + 'static>,
-    ) -> Vec<MagicEntryGen> {
-        let start_range = slider.possible_squares();
+        pieces: &[verify::Piece],
+    ) -> (Vec<MagicEntryGen>, Vec<BitBoard>) {
+        let slider = &pieces[0];
+        let mut start_range = slider.possible_squares();
+        // The search time grows quickly with the number of relevant blockers, so the
+        // largest squares are handed out first and the small ones fill in the gaps
+        // at the end, instead of one thread finishing a corner rook square alone.
+        start_range.sort_by_key(|&square| std::cmp::Reverse(verify::mask(pieces, square).popcnt()));
+        // In packed mode every square shares the shift of the largest one.
+        let fixed_bits = self
+            .packed
+            .then(|| verify::mask(pieces, start_range[0]).popcnt() as u8);
+        // Fall back to 128-bit magics when some mask does not fold.
+        let folds = start_range
+            .iter()
+            .all(|&square| self.hashing.supports(verify::mask(pieces, square)));
+        let hashing = if folds { self.hashing } else { Hashing::Wide };
+        let next = AtomicUsize::new(0);
         let mut table = Vec::new();
         let receiver = {
             let (sender, receiver) = mpsc::channel();
-            for square in start_range {
-                let slider = Arc::clone(&slider);
-                let rng = Arc::clone(&self.rng);
-                let sender = sender.clone();
-                rayon::spawn(move || {
-                    let g = FindMagicsWorker::find_and_print_step(slider, square, rng);
-                    sender.send(g).unwrap();
-                });
-            }
+            self.pool.scope(|scope| {
+                for _ in 0..self.pool.current_num_threads() {
+                    let sender = sender.clone();
+                    let (start_range, next, rng) = (&start_range, &next, &self.rng);
+                    let dense = self.dense;
+                    scope.spawn(move |_| {
+                        while let Some(&square) =
+                            start_range.get(next.fetch_add(1, Ordering::Relaxed))
+                        {
+                            // Each square draws from its own stream, so the magics only
+                            // depend on the seed and not on which thread finds them.
+                            let mut rng = rng.stream(square as u64);
+                            let g = FindMagicsWorker::find_and_print_step(
+                                pieces, square, &mut rng, fixed_bits, dense, hashing,
+                            );
+                            sender.send(g).unwrap();
+                        }
+                    });
+                }
+            });
             receiver
         };
         for g in receiver {
             table.push(g);
         }
-        table
+        let pieces: Vec<&dyn ChessMove> = pieces.iter().map(|piece| &**piece as _).collect();
+        if self.packed {
+            let sparse = table
+                .into_iter()
+                .map(|(entry, _)| {
+                    let magic_entry = MagicEntry {
+                        mask: shared_mask(&pieces, entry.square),
+                        magic: entry.parse_magic().unwrap(),
+                        shift: entry.shift,
+                        offset: 0,
+                    };
+                    let sparse =
+                        try_make_sparse_shared_table(&pieces, entry.square, &magic_entry, hashing);
+                    (entry, sparse.unwrap())
+                })
+                .collect();
+            return pack_tables(sparse);
+        }
+        // In the final move generator, each table is concatenated into one contiguous table
+        // for convenience, so an offset is added to denote the start of each segment.
+        concat_tables(table)
     }
 
     fn find_and_print_step(
-        slider: Arc<dyn ChessMove + Send + Sync + 'static>,
+        pieces: &[verify::Piece],
         square: Square,
-        rng: Arc<Mutex<Rng>>,
-    ) -> MagicEntryGen {
-        let index_bits = slider.relevant_blockers(square).popcnt() as u8;
-        let (entry, _) = find_magic(&*slider, square, index_bits, rng);
-        // In the final move generator, each table is concatenated into one contiguous table
-        // for convenience, so an offset is added to denote the start of each segment.
-        return entry;
+        rng: &mut Rng,
+        fixed_bits: Option<u8>,
+        dense: Option<Duration>,
+        hashing: Hashing,
+    ) -> (MagicEntryGen, Vec<BitBoard>) {
+        let pieces: Vec<&dyn ChessMove> = pieces.iter().map(|piece| &**piece as _).collect();
+        let index_bits = fixed_bits.unwrap_or_else(|| shared_mask(&pieces, square).popcnt() as u8);
+        let mut best = find_shared_magic(&pieces, square, index_bits, hashing, rng);
+        // Blocker subsets sharing a move set may share a slot, so smaller tables
+        // often exist. Keep shrinking until the budget of the square runs out.
+        if let Some(budget) = dense {
+            let deadline = Instant::now() + budget;
+            let min_bits = pieces
+                .iter()
+                .map(|piece| min_index_bits(*piece, square))
+                .max()
+                .unwrap()
+                .max(1);
+            for index_bits in (min_bits..index_bits).rev() {
+                match find_shared_magic_before(&pieces, square, index_bits, hashing, rng, deadline)
+                {
+                    Some(found) => best = found,
+                    None => break,
+                }
+            }
+        }
+        best
     }
 }
 
@@ -66,21 +169,68 @@ enum TasksOption {
     Nothing,
 }
 
+type Task<'a> = Box<dyn Fn(&mut FindMagicsWorker) -> Result<PieceTable, Error> + 'a>;
+
 struct TasksManage<'a> {
     worker: FindMagicsWorker,
-    tasks: HashMap<String, Box<dyn Fn(&mut FindMagicsWorker) -> Vec<MagicEntryGen> + 'a>>,
+    tasks: HashMap<String, Task<'a>>,
 }
 
+// The payloads are only reported through `Debug` when `main` fails.
+#[allow(dead_code)]
 #[derive(Debug)]
 enum Error {
     TaskNoFound,
     DoNoThing,
-    ThreadPoolBuildError,
+    ThreadPoolBuild,
+    Load(LoadError),
+    Binary(BinaryError),
+    Json(serde_json::Error),
+    Utf8(std::str::Utf8Error),
+    Io(io::Error),
+    VerifyFailed,
+    Fen(FenError),
 }
 
 impl From<rayon::ThreadPoolBuildError> for Error {
     fn from(_: rayon::ThreadPoolBuildError) -> Self {
-        Self::ThreadPoolBuildError
+        Self::ThreadPoolBuild
+    }
+}
+
+impl From<LoadError> for Error {
+    fn from(error: LoadError) -> Self {
+        Self::Load(error)
+    }
+}
+
+impl From<BinaryError> for Error {
+    fn from(error: BinaryError) -> Self {
+        Self::Binary(error)
+    }
+}
+
+impl From<FenError> for Error {
+    fn from(error: FenError) -> Self {
+        Self::Fen(error)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error)
+    }
+}
+
+impl From<std::str::Utf8Error> for Error {
+    fn from(error: std::str::Utf8Error) -> Self {
+        Self::Utf8(error)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
     }
 }
 
@@ -92,29 +242,63 @@ impl<'a> TasksManage<'a> {
         }
     }
 
-    fn insert(&mut self, name: &str, task: Arc<dyn ChessMove + Send + Sync + 'static>) {
+    fn insert(&mut self, name: &str, task: Vec<verify::Piece>) {
         let name = name.to_lowercase();
         self.tasks.insert(
-            name,
+            name.clone(),
             Box::new(move |worker: &mut FindMagicsWorker| {
-                worker.find_and_print_all_magics(task.clone())
+                let (magics, attacks) = worker.find_and_print_all_magics(&task);
+                if magics
+                    .first()
+                    .is_some_and(|gen| gen.hashing != worker.hashing)
+                {
+                    eprintln!("{}: masks do not fold, using 128-bit magics", name);
+                }
+                let stride = task.len();
+                let bytes = |size| size * stride * std::mem::size_of::<BitBoard>();
+                // Today's layout: one table per square, as large as its mask allows.
+                let per_square: usize = magics
+                    .iter()
+                    .map(|gen| bytes(1 << verify::mask(&task, gen.square).popcnt()))
+                    .sum();
+                if worker.dense.is_some() {
+                    let after: usize = magics.iter().map(|gen| bytes(gen.size)).sum();
+                    eprintln!("{}: {} -> {} table bytes", name, per_square, after);
+                }
+                if worker.packed {
+                    let packed = attacks.len() * std::mem::size_of::<BitBoard>();
+                    if packed < per_square {
+                        eprintln!(
+                            "{}: {} -> {} table bytes packed, {} saved",
+                            name,
+                            per_square,
+                            packed,
+                            per_square - packed
+                        );
+                    } else {
+                        eprintln!(
+                            "{}: {} -> {} table bytes packed, no saving",
+                            name, per_square, packed
+                        );
+                    }
+                }
+                let pieces: Vec<&dyn ChessMove> = task.iter().map(|piece| &**piece as _).collect();
+                let table = MagicTable::from_interleaved_attacks(&pieces, &magics, attacks)?;
+                Ok(PieceTable {
+                    name: name.clone(),
+                    magics,
+                    table,
+                })
             }),
         );
     }
 
-    fn run(
-        &mut self,
-        tasks_option: TasksOption,
-    ) -> Result<HashMap<String, Vec<MagicEntryGen>>, Error> {
-        let mut tables = HashMap::<String, Vec<MagicEntryGen>>::new();
+    fn run(&mut self, tasks_option: TasksOption) -> Result<Vec<PieceTable>, Error> {
         match tasks_option {
             TasksOption::Task(name) => {
                 let name = name.to_lowercase();
-                if let Some(task) = self.tasks.get_mut(&name) {
-                    let table = task(&mut self.worker);
-                    let name = format!("{}_magic_table", name);
-                    tables.insert(name, table);
-                    Ok(tables)
+                if let Some(task) = self.tasks.get(&name) {
+                    Ok(vec![task(&mut self.worker)?])
                 } else {
                     Err(Error::TaskNoFound)
                 }
@@ -123,11 +307,11 @@ impl<'a> TasksManage<'a> {
                 if self.tasks.is_empty() {
                     Err(Error::DoNoThing)
                 } else {
-                    for (name, task) in self.tasks.iter() {
-                        let table = task(&mut self.worker);
-                        let name = format!("{}_magic_table", name);
-                        tables.insert(name, table);
+                    let mut tables = Vec::new();
+                    for task in self.tasks.values() {
+                        tables.push(task(&mut self.worker)?);
                     }
+                    tables.sort_by(|a, b| a.name.cmp(&b.name));
                     Ok(tables)
                 }
             }
@@ -137,36 +321,256 @@ impl<'a> TasksManage<'a> {
 }
 
 #[derive(Parser)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, args_conflicts_with_subcommands = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
     /// Optional task_name to operate on
     task_name: Option<String>,
     /// number of thread
     #[arg(short, long, value_name = "N_JOBS")]
     jobs: Option<usize>,
+    /// seed of the magic search, the same seed gives the same tables
+    #[arg(short, long, default_value_t = 0)]
+    seed: u128,
+    /// also search each square for up to MILLIS ms for magics with fewer index bits
+    #[arg(short, long, value_name = "MILLIS")]
+    dense: Option<u64>,
+    /// use one shift for all squares of a piece and let their tables overlap
+    #[arg(short, long, conflicts_with = "dense")]
+    packed: bool,
+    /// fold the blockers into 64 bits and search 64-bit magics instead of 128-bit ones
+    #[arg(long)]
+    folded: bool,
+    /// also print the concatenated attack table of each piece
+    #[arg(short, long)]
+    attacks: bool,
+    /// output format
+    #[arg(short, long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+    /// write to FILE instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Rebuild every table of a JSON or binary file and check it against the pieces
+    Verify {
+        /// File previously written by magic-gen
+        file: PathBuf,
+    },
+    /// Count the leaf nodes of the legal move tree of a position
+    Perft {
+        /// number of plies
+        depth: u32,
+        /// FEN of the position, the start position by default
+        #[arg(short, long)]
+        fen: Option<String>,
+        /// also print the count after each root move
+        #[arg(short, long)]
+        divide: bool,
+        /// use the tables of a file previously written by magic-gen
+        #[arg(short, long, value_name = "FILE")]
+        tables: Option<PathBuf>,
+    },
+    /// Time rook and cannon lookups with magic and kindergarten tables
+    Bench {
+        /// File previously written by magic-gen
+        file: PathBuf,
+        /// number of lookups per piece and backend
+        #[arg(short, long, default_value_t = 10_000_000)]
+        iterations: usize,
+    },
+}
+
+fn pieces() -> Vec<(&'static str, verify::Piece)> {
+    vec![
+        ("ROOK", Arc::new(rook())),
+        ("CANNON", Arc::new(cannon())),
+        ("CANNON_QUIET", Arc::new(cannon_quiet())),
+        ("KNIGHT", Arc::new(knight())),
+        ("BISHOP", Arc::new(bishop())),
+        ("KING", Arc::new(king())),
+        ("ADVISOR", Arc::new(advisor())),
+        ("RED_PAWN", Arc::new(red_pawn())),
+        ("BLACK_PAWN", Arc::new(black_pawn())),
+        ("KING_STEP", Arc::new(king_step())),
+        ("KNIGHT_ATTACKERS", Arc::new(knight_attackers())),
+        ("ROOK_XRAY", Arc::new(rook_xray())),
+        ("CANNON_XRAY", Arc::new(cannon_xray())),
+    ]
+}
+
+// Pieces sharing one magic per square, with their attacks interleaved.
+fn shared_pieces() -> Vec<(&'static str, Vec<verify::Piece>)> {
+    vec![("ROOK_CANNON", vec![Arc::new(rook()), Arc::new(cannon())])]
+}
+
+fn all_pieces() -> Vec<(&'static str, Vec<verify::Piece>)> {
+    let single = pieces()
+        .into_iter()
+        .map(|(name, piece)| (name, vec![piece]));
+    single.chain(shared_pieces()).collect()
+}
+
+fn find_pieces(name: &str) -> Option<Vec<verify::Piece>> {
+    all_pieces()
+        .into_iter()
+        .find(|(piece, _)| piece.eq_ignore_ascii_case(name))
+        .map(|(_, pieces)| pieces)
+}
+
+fn verify(file: &PathBuf) -> Result<(), Error> {
+    let data = std::fs::read(file)?;
+    let report = if data.starts_with(&MAGIC) {
+        verify::verify_binary(&data, find_pieces)?
+    } else {
+        let tables: TablesGen = serde_json::from_slice(&data)?;
+        verify::verify_json(&tables, find_pieces)
+    };
+    let mut failed = false;
+    for (name, problems) in report {
+        if problems.is_empty() {
+            println!("{}: ok", name);
+        }
+        for problem in problems {
+            failed = true;
+            println!("{}: {}", name, problem);
+        }
+    }
+    if failed {
+        Err(Error::VerifyFailed)
+    } else {
+        Ok(())
+    }
+}
+
+fn load_lookup(data: &[u8]) -> Result<Lookup, Error> {
+    if data.starts_with(&MAGIC) {
+        Ok(Lookup::from_binary(data)?)
+    } else {
+        Ok(Lookup::from_json(std::str::from_utf8(data)?)?)
+    }
+}
+
+fn bench(file: &PathBuf, iterations: usize) -> Result<(), Error> {
+    let data = std::fs::read(file)?;
+    let magic = load_lookup(&data)?;
+    let kindergarten = load_lookup(&data)?
+        .with_kindergarten_rook()
+        .with_kindergarten_cannon();
+    let mut rng = Rng::default();
+    let positions: Vec<_> = (0..4096)
+        .map(|_| {
+            let square = Square::index(rng.next_u64() as usize % Square::NUM);
+            let occupied = rng.next_u128() & rng.next_u128() & ((1 << Square::NUM) - 1);
+            (square, BitBoard(occupied))
+        })
+        .collect();
+    type Moves = fn(&Lookup, Square, BitBoard) -> BitBoard;
+    let pieces: [(&str, Moves); 4] = [
+        ("rook", Lookup::rook_moves),
+        ("cannon", Lookup::cannon_captures),
+        ("cannon_quiet", Lookup::cannon_quiets),
+        ("rook_cannon", |lookup, square, occupied| {
+            let (rook, cannon) = lookup.rook_cannon_attacks(square, occupied);
+            rook | cannon
+        }),
+    ];
+    for (piece, moves) in pieces {
+        for (backend, lookup) in [("magic", &magic), ("kindergarten", &kindergarten)] {
+            let start = Instant::now();
+            let mut seen = BitBoard::EMPTY;
+            for i in 0..iterations {
+                let (square, occupied) = positions[i % positions.len()];
+                seen ^= moves(lookup, square, std::hint::black_box(occupied));
+            }
+            std::hint::black_box(seen);
+            let elapsed = start.elapsed().as_nanos() as f64 / iterations.max(1) as f64;
+            println!("{} {}: {:.2} ns/lookup", piece, backend, elapsed);
+        }
+    }
+    Ok(())
+}
+
+fn perft(
+    depth: u32,
+    fen: Option<&str>,
+    divide: bool,
+    tables: Option<&PathBuf>,
+) -> Result<(), Error> {
+    let position = Position::from_fen(fen.unwrap_or(Position::START_FEN))?;
+    let loaded = match tables {
+        Some(file) => Some(load_lookup(&std::fs::read(file)?)?),
+        None => None,
+    };
+    let lookup = loaded.as_ref().unwrap_or_else(|| movegen::lookup());
+    let start = Instant::now();
+    let nodes = if divide {
+        let mut divided = perft::divide(lookup, &position, depth);
+        divided.sort_by_key(|(mv, _)| mv.to_iccs());
+        for (mv, nodes) in &divided {
+            println!("{}: {}", mv, nodes);
+        }
+        divided.iter().map(|(_, nodes)| nodes).sum()
+    } else {
+        perft::perft(lookup, &position, depth)
+    };
+    let elapsed = start.elapsed();
+    println!("nodes: {}", nodes);
+    println!(
+        "time: {:.3} s, {:.0} nodes/s",
+        elapsed.as_secs_f64(),
+        nodes as f64 / elapsed.as_secs_f64().max(1e-9)
+    );
+    Ok(())
 }
 
 fn main() -> Result<(), Error> {
     let cli = Cli::parse();
+    match &cli.command {
+        Some(Command::Verify { file }) => return verify(file),
+        Some(Command::Bench { file, iterations }) => return bench(file, *iterations),
+        Some(Command::Perft {
+            depth,
+            fen,
+            divide,
+            tables,
+        }) => return perft(*depth, fen.as_deref(), *divide, tables.as_ref()),
+        None => {}
+    }
     let task_name = cli.task_name.as_deref();
-    let worker = FindMagicsWorker::new();
-    if let Some(thread_count) = cli.jobs {
-        rayon::ThreadPoolBuilder::default()
-            .num_threads(thread_count)
-            .build()?;
-    };
+    let worker = FindMagicsWorker::new(
+        cli.jobs,
+        cli.seed,
+        cli.dense.map(Duration::from_millis),
+        cli.packed,
+        if cli.folded {
+            Hashing::Folded
+        } else {
+            Hashing::Wide
+        },
+    )?;
     let task = match task_name {
         Some(name) if name != "none" => TasksOption::Task(name.to_string()),
         Some(_) => TasksOption::Nothing,
         None => TasksOption::All,
     };
     let mut tasks_manage = TasksManage::new(worker);
-    tasks_manage.insert("ROOK", Arc::new(rook()));
-    tasks_manage.insert("CANNON", Arc::new(cannon()));
-    tasks_manage.insert("KNIGHT", Arc::new(knight()));
-    tasks_manage.insert("BISHOP", Arc::new(bishop()));
-    tasks_manage.insert("KING", Arc::new(king()));
+    for (name, pieces) in all_pieces() {
+        tasks_manage.insert(name, pieces);
+    }
     let tables = tasks_manage.run(task)?;
-    println!("{}", serde_json::to_string(&tables).unwrap());
+    let mut out: Box<dyn Write> = match &cli.output {
+        Some(path) => Box::new(io::BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    match cli.format {
+        Format::Json => format::write_json(&mut out, &tables, cli.attacks)?,
+        Format::Rust => format::write_rust(&mut out, &tables, cli.attacks)?,
+        Format::Binary => format::write_binary(&mut out, &tables)?,
+    }
+    out.flush()?;
     Ok(())
 }
@@ -0,0 +1,258 @@
+use std::fmt;
+
+use types::{BitBoard, Square};
+use xq::{
+    binary::{self, BinaryError},
+    generate::{parse_hex, shared_mask, ChessMove, Hashing, MagicEntry, TableGen, TablesGen},
+};
+
+pub type Piece = std::sync::Arc<dyn ChessMove + Send + Sync + 'static>;
+
+pub enum Problem {
+    UnknownPiece(String),
+    InvalidTable(String),
+    MissingSquare(Square),
+    InvalidEntry(Square),
+    InvalidAttack(usize),
+    MaskMismatch {
+        square: Square,
+        stored: BitBoard,
+        expected: BitBoard,
+    },
+    // Blocker subsets with different moves that hash to an already used slot.
+    Collisions {
+        square: Square,
+        count: usize,
+    },
+    // Stored attacks that differ from `ChessMove::moves`.
+    AttackMismatches {
+        square: Square,
+        count: usize,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownPiece(name) => write!(f, "unknown table {}", name),
+            Self::InvalidTable(name) => write!(f, "{}_magic_table does not hold magics", name),
+            Self::MissingSquare(square) => write!(f, "{:?}: no magic", square),
+            Self::InvalidEntry(square) => write!(f, "{:?}: invalid magic, shift or offset", square),
+            Self::InvalidAttack(index) => write!(f, "attack {}: not a hex number", index),
+            Self::MaskMismatch {
+                square,
+                stored,
+                expected,
+            } => write!(
+                f,
+                "{:?}: mask {:?} does not match relevant blockers {:?}",
+                square, stored, expected
+            ),
+            Self::Collisions { square, count } => {
+                write!(f, "{:?}: {} colliding blocker subsets", square, count)
+            }
+            Self::AttackMismatches { square, count } => {
+                write!(
+                    f,
+                    "{:?}: {} stored attacks differ from moves",
+                    square, count
+                )
+            }
+        }
+    }
+}
+
+// Checks one square the way `try_make_shared_table` fills it, but keeps going
+// after the first failure so every problem of the square gets counted.
+fn verify_square(
+    pieces: &[Piece],
+    square: Square,
+    entry: &MagicEntry,
+    hashing: Hashing,
+    attacks: Option<&[BitBoard]>,
+    problems: &mut Vec<Problem>,
+) {
+    let expected = mask(pieces, square);
+    let stride = pieces.len();
+    if entry.mask != expected {
+        problems.push(Problem::MaskMismatch {
+            square,
+            stored: entry.mask,
+            expected,
+        });
+    }
+    let Some(size) = valid_shift(hashing, entry.shift).map(|index_bits| 1usize << index_bits)
+    else {
+        problems.push(Problem::InvalidEntry(square));
+        return;
+    };
+    if !hashing.valid_magic(entry.mask, entry.magic) {
+        problems.push(Problem::InvalidEntry(square));
+        return;
+    }
+    let end = size
+        .checked_mul(stride)
+        .and_then(|len| entry.offset.checked_add(len));
+    if attacks.is_some_and(|attacks| end.is_none_or(|end| end > attacks.len())) {
+        problems.push(Problem::InvalidEntry(square));
+        return;
+    }
+    let mut table = vec![None; size];
+    let (mut collisions, mut mismatches) = (0, 0);
+    let mut blockers = BitBoard::EMPTY;
+    loop {
+        let moves: Vec<_> = pieces
+            .iter()
+            .map(|piece| piece.moves(square, blockers))
+            .collect();
+        let index = hashing.index(entry, blockers);
+        match &table[index] {
+            None => table[index] = Some(moves.clone()),
+            Some(stored) if *stored != moves => collisions += 1,
+            Some(_) => {}
+        }
+        if let Some(attacks) = attacks {
+            let stored = &attacks[entry.offset + index * stride..][..stride];
+            if stored != moves {
+                mismatches += 1;
+            }
+        }
+        blockers.0 = blockers.0.wrapping_sub(expected.0) & expected.0;
+        if blockers.is_empty() {
+            break;
+        }
+    }
+    if collisions > 0 {
+        problems.push(Problem::Collisions {
+            square,
+            count: collisions,
+        });
+    }
+    if mismatches > 0 {
+        problems.push(Problem::AttackMismatches {
+            square,
+            count: mismatches,
+        });
+    }
+}
+
+// Relevant blockers `pieces` share on `square`.
+pub fn mask(pieces: &[Piece], square: Square) -> BitBoard {
+    let pieces: Vec<&dyn ChessMove> = pieces.iter().map(|piece| &**piece as _).collect();
+    shared_mask(&pieces, square)
+}
+
+// Index bits of `shift`, if it is in range for `hashing`.
+fn valid_shift(hashing: Hashing, shift: u8) -> Option<u8> {
+    hashing
+        .index_bits(shift)
+        .filter(|&index_bits| index_bits <= 32)
+}
+
+// Verifies every `<name>_magic_table` of a JSON file, and its
+// `<name>_attack_table` when present.
+pub fn verify_json(
+    tables: &TablesGen,
+    find_pieces: impl Fn(&str) -> Option<Vec<Piece>>,
+) -> Vec<(String, Vec<Problem>)> {
+    let mut names: Vec<_> = tables
+        .keys()
+        .filter_map(|name| name.strip_suffix("_magic_table"))
+        .collect();
+    names.sort();
+    let mut report = Vec::new();
+    for name in names {
+        let mut problems = Vec::new();
+        let magics = match &tables[&format!("{}_magic_table", name)] {
+            TableGen::Magics(magics) => magics,
+            TableGen::Attacks(_) => {
+                problems.push(Problem::InvalidTable(name.to_string()));
+                report.push((name.to_string(), problems));
+                continue;
+            }
+        };
+        let Some(pieces) = find_pieces(name) else {
+            problems.push(Problem::UnknownPiece(name.to_string()));
+            report.push((name.to_string(), problems));
+            continue;
+        };
+        let attacks = match tables.get(&format!("{}_attack_table", name)) {
+            Some(TableGen::Attacks(attacks)) => {
+                let mut parsed = Vec::with_capacity(attacks.len());
+                for (i, attack) in attacks.iter().enumerate() {
+                    match parse_hex(attack) {
+                        Some(attack) => parsed.push(BitBoard(attack)),
+                        None => problems.push(Problem::InvalidAttack(i)),
+                    }
+                }
+                Some(parsed)
+            }
+            _ => None,
+        };
+        for square in pieces[0].possible_squares() {
+            let Some(gen) = magics.iter().find(|gen| gen.square == square) else {
+                problems.push(Problem::MissingSquare(square));
+                continue;
+            };
+            let magic = match gen.parse_magic() {
+                Some(magic)
+                    if valid_shift(gen.hashing, gen.shift)
+                        .is_some_and(|index_bits| gen.size == 1 << index_bits) =>
+                {
+                    magic
+                }
+                _ => {
+                    problems.push(Problem::InvalidEntry(square));
+                    continue;
+                }
+            };
+            let entry = MagicEntry {
+                mask: mask(&pieces, square),
+                magic,
+                shift: gen.shift,
+                offset: gen.offset,
+            };
+            verify_square(
+                &pieces,
+                square,
+                &entry,
+                gen.hashing,
+                attacks.as_deref(),
+                &mut problems,
+            );
+        }
+        report.push((name.to_string(), problems));
+    }
+    report
+}
+
+// Verifies every section of a binary file, including the stored masks.
+pub fn verify_binary(
+    data: &[u8],
+    find_pieces: impl Fn(&str) -> Option<Vec<Piece>>,
+) -> Result<Vec<(String, Vec<Problem>)>, BinaryError> {
+    let mut report = Vec::new();
+    for table in binary::read_tables(data)? {
+        let name = table.kind().name();
+        let mut problems = Vec::new();
+        match find_pieces(name) {
+            Some(pieces) => {
+                let attacks = table.to_magic_table();
+                for square in pieces[0].possible_squares() {
+                    let entry = table.entry(square);
+                    verify_square(
+                        &pieces,
+                        square,
+                        &entry,
+                        table.hashing(),
+                        Some(attacks.attacks()),
+                        &mut problems,
+                    );
+                }
+            }
+            None => problems.push(Problem::UnknownPiece(name.to_string())),
+        }
+        report.push((name.to_string(), problems));
+    }
+    Ok(report)
+}
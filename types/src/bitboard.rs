@@ -2,11 +2,6 @@ use super::*;
 
 use std::ops::*;
 
-pub enum Color {
-    Red,
-    Black,
-}
-
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default, Hash)]
 pub struct BitBoard(pub u128);
 
@@ -52,6 +47,7 @@ impl Not for BitBoard {
 
 impl BitBoard {
     pub const EMPTY: Self = Self(0);
+    pub const FULL: Self = Self((1 << Square::NUM) - 1);
 
     pub fn popcnt(self) -> u32 {
         self.0.count_ones()
@@ -0,0 +1,218 @@
+use std::{fmt, str::FromStr};
+
+use super::*;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    // Fewer than the board and side to move, or more than six fields.
+    FieldCount(usize),
+    RankCount(usize),
+    // The rank does not describe exactly nine files.
+    RankLength(Rank),
+    UnknownPiece(char),
+    KingCount { color: Color, count: u32 },
+    SideToMove(String),
+    HalfmoveClock(String),
+    FullmoveNumber(String),
+}
+
+impl Position {
+    // The standard starting position.
+    pub const START_FEN: &'static str =
+        "rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR w - - 0 1";
+
+    pub fn start() -> Self {
+        Self::from_fen(Self::START_FEN).unwrap()
+    }
+
+    // Parses `<board> <side> [- - [halfmove [fullmove]]]`. Ranks are listed
+    // from the black side down, and the side to move is `w` or `r` for red.
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let fields: Vec<_> = fen.split_whitespace().collect();
+        if !(2..=6).contains(&fields.len()) {
+            return Err(FenError::FieldCount(fields.len()));
+        }
+        let mut position = Position::empty();
+        let ranks: Vec<_> = fields[0].split('/').collect();
+        if ranks.len() != Rank::NUM {
+            return Err(FenError::RankCount(ranks.len()));
+        }
+        for (&rank, row) in Rank::ALL.iter().rev().zip(ranks) {
+            let mut file = 0;
+            for c in row.chars() {
+                if let Some(empty) = c.to_digit(10) {
+                    file += empty as usize;
+                } else {
+                    let (piece, color) =
+                        Piece::try_from_char(c).ok_or(FenError::UnknownPiece(c))?;
+                    let square = Square::new(
+                        File::try_index(file).ok_or(FenError::RankLength(rank))?,
+                        rank,
+                    );
+                    position.put_piece(square, piece, color).unwrap();
+                    file += 1;
+                }
+                if file > File::NUM {
+                    return Err(FenError::RankLength(rank));
+                }
+            }
+            if file != File::NUM {
+                return Err(FenError::RankLength(rank));
+            }
+        }
+        for color in Color::ALL {
+            let count = position.colored_pieces(color, Piece::King).popcnt();
+            if count != 1 {
+                return Err(FenError::KingCount { color, count });
+            }
+        }
+        let side_to_move = match fields[1] {
+            "w" | "r" => Color::Red,
+            "b" => Color::Black,
+            side => return Err(FenError::SideToMove(side.to_string())),
+        };
+        position.set_side_to_move(side_to_move);
+        if let Some(&clock) = fields.get(4) {
+            let clock = clock
+                .parse()
+                .map_err(|_| FenError::HalfmoveClock(clock.to_string()))?;
+            position.set_halfmove_clock(clock);
+        }
+        if let Some(&number) = fields.get(5) {
+            let number = number
+                .parse()
+                .ok()
+                .filter(|&number| number > 0)
+                .ok_or_else(|| FenError::FullmoveNumber(number.to_string()))?;
+            position.set_fullmove_number(number);
+        }
+        Ok(position)
+    }
+
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        for &rank in Rank::ALL.iter().rev() {
+            let mut empty = 0;
+            for file in File::ALL {
+                let square = Square::new(file, rank);
+                match (self.piece_on(square), self.color_on(square)) {
+                    (Some(piece), Some(color)) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(piece.to_char(color));
+                    }
+                    _ => empty += 1,
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank != Rank::First {
+                fen.push('/');
+            }
+        }
+        let side = match self.side_to_move() {
+            Color::Red => 'w',
+            Color::Black => 'b',
+        };
+        format!(
+            "{} {} - - {} {}",
+            fen,
+            side,
+            self.halfmove_clock(),
+            self.fullmove_number()
+        )
+    }
+}
+
+impl FromStr for Position {
+    type Err = FenError;
+
+    fn from_str(fen: &str) -> Result<Self, Self::Err> {
+        Self::from_fen(fen)
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_fen())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fen_round_trip() {
+        let fens = [
+            Position::START_FEN,
+            "r1bakab1r/9/1cn4cn/p1p1p1p1p/9/6P2/P1P1P3P/1C2C1N2/9/RNBAKAB1R b - - 3 2",
+            "3k5/9/9/9/9/9/9/9/4A4/4K4 w - - 0 60",
+        ];
+        for fen in fens {
+            let position = Position::from_fen(fen).unwrap();
+            assert!(position.validate().is_ok());
+            assert!(position.to_fen() == fen, "fail with fen = {}!", fen);
+        }
+    }
+
+    #[test]
+    fn test_fen_start() {
+        let position = Position::start();
+        assert!(position.occupied().popcnt() == 32);
+        assert!(position.piece_on(Square::B3) == Some(Piece::Cannon));
+        assert!(position.color_on(Square::B3) == Some(Color::Red));
+        assert!(position.piece_on(Square::E0) == Some(Piece::King));
+        assert!(position.color_on(Square::E0) == Some(Color::Black));
+        assert!(position.side_to_move() == Color::Red);
+    }
+
+    #[test]
+    fn test_fen_errors() {
+        let errors = [
+            ("rnbakabnr/9/1c5c1", FenError::FieldCount(1)),
+            (
+                "rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/RNBAKABNR w",
+                FenError::RankCount(9),
+            ),
+            (
+                "rnbakabnr/9/1c5c1/p1p1p1p1p/9/8/P1P1P1P1P/1C5C1/9/RNBAKABNR w",
+                FenError::RankLength(Rank::Fifth),
+            ),
+            (
+                "rnbakabnrr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR w",
+                FenError::RankLength(Rank::Tenth),
+            ),
+            (
+                "rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNX w",
+                FenError::UnknownPiece('X'),
+            ),
+            (
+                "rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBA1ABNR w",
+                FenError::KingCount {
+                    color: Color::Red,
+                    count: 0,
+                },
+            ),
+            (
+                "rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR x",
+                FenError::SideToMove("x".to_string()),
+            ),
+            (
+                "rnbakabnr/9/1c5c1/p1p1p1p1p/9/9/P1P1P1P1P/1C5C1/9/RNBAKABNR w - - -1 1",
+                FenError::HalfmoveClock("-1".to_string()),
+            ),
+        ];
+        for (fen, error) in errors {
+            assert!(
+                Position::from_fen(fen) == Err(error.clone()),
+                "fail with fen = {}, expected {:?}!",
+                fen,
+                error
+            );
+        }
+    }
+}
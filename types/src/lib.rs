@@ -1,5 +1,16 @@
 mod bitboard;
+#[macro_use]
 mod square;
+mod fen;
+mod line;
+mod moves;
+mod piece;
+mod position;
 
 pub use bitboard::*;
+pub use fen::*;
+pub use line::*;
+pub use moves::*;
+pub use piece::*;
+pub use position::*;
 pub use square::*;
@@ -0,0 +1,111 @@
+use super::*;
+
+const fn on_board(file: i32, rank: i32) -> bool {
+    0 <= file && file < File::NUM as i32 && 0 <= rank && rank < Rank::NUM as i32
+}
+
+// Squares of the rank, file or diagonal from `a` towards `b`: those strictly
+// between the two, or with `whole` the entire line through both. Empty when
+// the squares are equal or not aligned.
+const fn aligned(a: usize, b: usize, whole: bool) -> u128 {
+    let (af, ar) = ((a % File::NUM) as i32, (a / File::NUM) as i32);
+    let (bf, br) = ((b % File::NUM) as i32, (b / File::NUM) as i32);
+    let (df, dr) = (bf - af, br - ar);
+    if a == b || !(df == 0 || dr == 0 || df.abs() == dr.abs()) {
+        return 0;
+    }
+    let (sf, sr) = (df.signum(), dr.signum());
+    let mut bits = 0;
+    if whole {
+        let (mut f, mut r) = (af, ar);
+        while on_board(f - sf, r - sr) {
+            f -= sf;
+            r -= sr;
+        }
+        while on_board(f, r) {
+            bits |= 1 << (f + r * File::NUM as i32);
+            f += sf;
+            r += sr;
+        }
+    } else {
+        let (mut f, mut r) = (af + sf, ar + sr);
+        while f != bf || r != br {
+            bits |= 1 << (f + r * File::NUM as i32);
+            f += sf;
+            r += sr;
+        }
+    }
+    bits
+}
+
+const fn aligned_table(whole: bool) -> [[BitBoard; Square::NUM]; Square::NUM] {
+    let mut table = [[BitBoard::EMPTY; Square::NUM]; Square::NUM];
+    let mut a = 0;
+    while a < Square::NUM {
+        let mut b = 0;
+        while b < Square::NUM {
+            table[a][b] = BitBoard(aligned(a, b, whole));
+            b += 1;
+        }
+        a += 1;
+    }
+    table
+}
+
+static BETWEEN: [[BitBoard; Square::NUM]; Square::NUM] = aligned_table(false);
+static LINE: [[BitBoard; Square::NUM]; Square::NUM] = aligned_table(true);
+
+// Squares strictly between `a` and `b` on a rank, file or diagonal, such as
+// the eye of a bishop move. Empty when they are not aligned.
+pub fn between(a: Square, b: Square) -> BitBoard {
+    BETWEEN[a as usize][b as usize]
+}
+
+// The whole rank, file or diagonal through `a` and `b`, both included.
+// Empty when they are equal or not aligned.
+pub fn line(a: Square, b: Square) -> BitBoard {
+    LINE[a as usize][b as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_between() {
+        assert!(between(Square::E1, Square::E4) == Square::E2.bitboard() | Square::E3.bitboard());
+        assert!(between(Square::I3, Square::F3) == Square::H3.bitboard() | Square::G3.bitboard());
+        // The eye of a bishop and the diagonal step of an advisor.
+        assert!(between(Square::C1, Square::E3) == Square::D2.bitboard());
+        assert!(between(Square::D1, Square::E2).is_empty());
+        assert!(between(Square::B1, Square::C3).is_empty());
+        assert!(between(Square::E5, Square::E5).is_empty());
+    }
+
+    #[test]
+    fn test_line() {
+        let board = bitboard! {
+            . . . . . . . . .
+            . . . . . . . . .
+            . . . . . . . . .
+            . . . . . . . . .
+            . . . . . . . . X
+            . . . . . . . X .
+            . . . . . . X . .
+            . . . . . X . . .
+            . . . . X . . . .
+            . . . X . . . . .
+        };
+        assert!(line(Square::E2, Square::G4) == board);
+        assert!(line(Square::G4, Square::D1) == board);
+        assert!(line(Square::A5, Square::C5).popcnt() == 9);
+        assert!(line(Square::A5, Square::A0).popcnt() == 10);
+        assert!(line(Square::A5, Square::B7).is_empty());
+        for a in Square::ALL {
+            for b in Square::ALL {
+                assert!((between(a, b) & !line(a, b)).is_empty());
+                assert!(line(a, b) == line(b, a));
+            }
+        }
+    }
+}
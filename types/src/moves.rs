@@ -0,0 +1,160 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+use super::*;
+
+// A move of the piece on `from` to `to`, and the piece it captures there.
+// Moves are equal when their squares are, so a move parsed from ICCS matches
+// the generated capture.
+#[derive(Debug, Clone, Copy)]
+pub struct Move {
+    pub from: Square,
+    pub to: Square,
+    pub captured: Option<Piece>,
+}
+
+impl PartialEq for Move {
+    fn eq(&self, other: &Self) -> bool {
+        self.from == other.from && self.to == other.to
+    }
+}
+
+impl Eq for Move {}
+
+impl Hash for Move {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.from.hash(state);
+        self.to.hash(state);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IccsError {
+    // Not four characters, with an optional `-` in the middle.
+    Length(usize),
+    InvalidSquare(String),
+}
+
+impl Move {
+    pub fn new(from: Square, to: Square) -> Self {
+        Move {
+            from,
+            to,
+            captured: None,
+        }
+    }
+
+    // `from` in bits 0..7 and `to` in bits 7..14; the capture is dropped.
+    pub fn to_u16(self) -> u16 {
+        self.from as u16 | (self.to as u16) << 7
+    }
+
+    pub fn from_u16(bits: u16) -> Option<Self> {
+        Some(Move::new(
+            Square::try_index(bits as usize & 0x7F)?,
+            Square::try_index((bits >> 7) as usize & 0x7F)?,
+        ))
+    }
+
+    // Like `to_u16`, plus the captured piece + 1 in bits 14..17, or 0 for none.
+    pub fn to_u32(self) -> u32 {
+        let captured = self.captured.map_or(0, |piece| piece as u32 + 1);
+        self.to_u16() as u32 | captured << 14
+    }
+
+    pub fn from_u32(bits: u32) -> Option<Self> {
+        let mut mv = Self::from_u16(bits as u16 & 0x3FFF)?;
+        mv.captured = match (bits >> 14) as usize {
+            0 => None,
+            piece => Some(Piece::try_index(piece - 1)?),
+        };
+        Some(mv)
+    }
+
+    // Parses ICCS coordinates such as `h2e2` or `H2-E2`. Files run `a` to `i`
+    // from red's left and ranks `0` to `9` from red's side of the board.
+    pub fn from_iccs(iccs: &str) -> Result<Self, IccsError> {
+        let chars: Vec<_> = iccs.chars().collect();
+        let chars = match chars[..] {
+            [a, b, '-', c, d] | [a, b, c, d] => [a, b, c, d],
+            _ => return Err(IccsError::Length(chars.len())),
+        };
+        let square = |file: char, rank: char| {
+            let file = (file.to_ascii_lowercase() as usize).checked_sub('a' as usize)?;
+            let rank = rank.to_digit(10)? as usize;
+            Some(Square::new(File::try_index(file)?, Rank::try_index(rank)?))
+        };
+        let invalid = || IccsError::InvalidSquare(iccs.to_string());
+        Ok(Move::new(
+            square(chars[0], chars[1]).ok_or_else(invalid)?,
+            square(chars[2], chars[3]).ok_or_else(invalid)?,
+        ))
+    }
+
+    pub fn to_iccs(self) -> String {
+        let square = |square: Square| {
+            let file = (b'a' + square.file() as u8) as char;
+            format!("{}{}", file, square.rank() as usize)
+        };
+        format!("{}{}", square(self.from), square(self.to))
+    }
+}
+
+impl FromStr for Move {
+    type Err = IccsError;
+
+    fn from_str(iccs: &str) -> Result<Self, Self::Err> {
+        Self::from_iccs(iccs)
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_iccs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_iccs() {
+        let mv = Move::from_iccs("h2e2").unwrap();
+        assert!(mv == Move::new(Square::H3, Square::E3));
+        assert!(mv.to_iccs() == "h2e2");
+        assert!(Move::from_iccs("A9-A0").unwrap() == Move::new(Square::A0, Square::A1));
+        assert!(Move::from_iccs("h2e").is_err());
+        assert!(Move::from_iccs("j2e2") == Err(IccsError::InvalidSquare("j2e2".to_string())));
+    }
+
+    #[test]
+    fn test_move_equality() {
+        let capture = Move {
+            captured: Some(Piece::Rook),
+            ..Move::new(Square::A1, Square::A2)
+        };
+        assert!(capture == Move::new(Square::A1, Square::A2));
+        assert!(capture != Move::new(Square::A1, Square::A3));
+    }
+
+    #[test]
+    fn test_move_encoding() {
+        for from in Square::ALL {
+            let mv = Move {
+                from,
+                to: Square::index(89 - from as usize),
+                captured: Piece::try_index(from as usize % 8),
+            };
+            let decoded = Move::from_u32(mv.to_u32()).unwrap();
+            assert!(decoded == mv && decoded.captured == mv.captured);
+            let decoded = Move::from_u16(mv.to_u16()).unwrap();
+            assert!(decoded == mv && decoded.captured.is_none());
+        }
+        assert!(Move::from_u16(90).is_none());
+        assert!(Move::from_u32(8 << 14).is_none());
+    }
+}
@@ -0,0 +1,68 @@
+use std::ops::Not;
+
+simple_enum! {
+    pub enum Color {
+        Red,
+        Black
+    }
+
+    pub enum Piece {
+        King,
+        Rook,
+        Knight,
+        Cannon,
+        Bishop,
+        Advisor,
+        Pawn
+    }
+}
+
+impl Not for Color {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        match self {
+            Self::Red => Self::Black,
+            Self::Black => Self::Red,
+        }
+    }
+}
+
+impl Piece {
+    // FEN letter, uppercase for red.
+    pub fn to_char(self, color: Color) -> char {
+        let c = match self {
+            Self::King => 'k',
+            Self::Rook => 'r',
+            Self::Knight => 'n',
+            Self::Cannon => 'c',
+            Self::Bishop => 'b',
+            Self::Advisor => 'a',
+            Self::Pawn => 'p',
+        };
+        match color {
+            Color::Red => c.to_ascii_uppercase(),
+            Color::Black => c,
+        }
+    }
+
+    // Also accepts `h` for the knight (horse) and `e` for the bishop (elephant).
+    pub fn try_from_char(c: char) -> Option<(Self, Color)> {
+        let piece = match c.to_ascii_lowercase() {
+            'k' => Self::King,
+            'r' => Self::Rook,
+            'n' | 'h' => Self::Knight,
+            'c' => Self::Cannon,
+            'b' | 'e' => Self::Bishop,
+            'a' => Self::Advisor,
+            'p' => Self::Pawn,
+            _ => return None,
+        };
+        let color = if c.is_ascii_uppercase() {
+            Color::Red
+        } else {
+            Color::Black
+        };
+        Some((piece, color))
+    }
+}
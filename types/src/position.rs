@@ -0,0 +1,231 @@
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PositionError {
+    SquareOccupied(Square),
+    // Squares claimed by both colors.
+    ColorsOverlap(BitBoard),
+    // Squares claimed by more than one piece type.
+    PiecesOverlap(BitBoard),
+    // Squares with a color but no piece type, or the other way around.
+    BoardsDisagree(BitBoard),
+    // Bits set above the 90 squares of the board.
+    OffBoard(BitBoard),
+}
+
+// A game position: one board per color and one per piece type, so the
+// pieces of one color and type are `colors[color] & pieces[piece]`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Position {
+    colors: [BitBoard; Color::NUM],
+    pieces: [BitBoard; Piece::NUM],
+    side_to_move: Color,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl Position {
+    // An empty board with red to move.
+    pub const fn empty() -> Self {
+        Position {
+            colors: [BitBoard::EMPTY; Color::NUM],
+            pieces: [BitBoard::EMPTY; Piece::NUM],
+            side_to_move: Color::Red,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+        }
+    }
+
+    pub fn colors(&self, color: Color) -> BitBoard {
+        self.colors[color as usize]
+    }
+
+    pub fn pieces(&self, piece: Piece) -> BitBoard {
+        self.pieces[piece as usize]
+    }
+
+    pub fn colored_pieces(&self, color: Color, piece: Piece) -> BitBoard {
+        self.colors(color) & self.pieces(piece)
+    }
+
+    pub fn occupied(&self) -> BitBoard {
+        self.colors(Color::Red) | self.colors(Color::Black)
+    }
+
+    pub fn piece_on(&self, square: Square) -> Option<Piece> {
+        Piece::ALL
+            .into_iter()
+            .find(|&piece| self.pieces(piece).has(square))
+    }
+
+    pub fn color_on(&self, square: Square) -> Option<Color> {
+        Color::ALL
+            .into_iter()
+            .find(|&color| self.colors(color).has(square))
+    }
+
+    pub fn king(&self, color: Color) -> Option<Square> {
+        self.colored_pieces(color, Piece::King).next_square()
+    }
+
+    pub fn side_to_move(&self) -> Color {
+        self.side_to_move
+    }
+
+    pub fn set_side_to_move(&mut self, color: Color) {
+        self.side_to_move = color;
+    }
+
+    // Plies since the last capture, for the no-capture draw rule.
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    pub fn set_halfmove_clock(&mut self, halfmove_clock: u32) {
+        self.halfmove_clock = halfmove_clock;
+    }
+
+    // Starts at 1 and grows after every black move.
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    pub fn set_fullmove_number(&mut self, fullmove_number: u32) {
+        self.fullmove_number = fullmove_number;
+    }
+
+    pub fn put_piece(
+        &mut self,
+        square: Square,
+        piece: Piece,
+        color: Color,
+    ) -> Result<(), PositionError> {
+        if self.occupied().has(square) {
+            return Err(PositionError::SquareOccupied(square));
+        }
+        self.colors[color as usize] |= square.bitboard();
+        self.pieces[piece as usize] |= square.bitboard();
+        Ok(())
+    }
+
+    // Empties `square`, returning what stood there.
+    pub fn remove_piece(&mut self, square: Square) -> Option<(Piece, Color)> {
+        let piece = self.piece_on(square)?;
+        let color = self.color_on(square)?;
+        self.colors[color as usize] ^= square.bitboard();
+        self.pieces[piece as usize] ^= square.bitboard();
+        Some((piece, color))
+    }
+
+    // Plays `mv` without checking that it is legal: the piece on `from`
+    // moves to `to`, capturing what stands there, and the other side moves next.
+    pub fn play_unchecked(&mut self, mv: Move) {
+        let (piece, color) = self.remove_piece(mv.from).expect("no piece to move");
+        let captured = self.remove_piece(mv.to);
+        self.put_piece(mv.to, piece, color).unwrap();
+        if captured.is_some() {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if self.side_to_move == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.side_to_move = !self.side_to_move;
+    }
+
+    // Checks that every occupied square has exactly one color and one piece type.
+    pub fn validate(&self) -> Result<(), PositionError> {
+        let red = self.colors(Color::Red);
+        let black = self.colors(Color::Black);
+        if !(red & black).is_empty() {
+            return Err(PositionError::ColorsOverlap(red & black));
+        }
+        let mut pieces = BitBoard::EMPTY;
+        let mut overlap = BitBoard::EMPTY;
+        for &board in &self.pieces {
+            overlap |= pieces & board;
+            pieces |= board;
+        }
+        if !overlap.is_empty() {
+            return Err(PositionError::PiecesOverlap(overlap));
+        }
+        if pieces != red | black {
+            return Err(PositionError::BoardsDisagree(pieces ^ (red | black)));
+        }
+        if !(pieces & !BitBoard::FULL).is_empty() {
+            return Err(PositionError::OffBoard(pieces & !BitBoard::FULL));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position_put_remove() {
+        let mut position = Position::empty();
+        position
+            .put_piece(Square::E1, Piece::King, Color::Red)
+            .unwrap();
+        position
+            .put_piece(Square::E0, Piece::King, Color::Black)
+            .unwrap();
+        position
+            .put_piece(Square::B3, Piece::Cannon, Color::Red)
+            .unwrap();
+        assert!(
+            position.put_piece(Square::B3, Piece::Rook, Color::Black)
+                == Err(PositionError::SquareOccupied(Square::B3))
+        );
+        assert!(position.piece_on(Square::B3) == Some(Piece::Cannon));
+        assert!(position.color_on(Square::E0) == Some(Color::Black));
+        assert!(position.king(Color::Red) == Some(Square::E1));
+        assert!(position.occupied().popcnt() == 3);
+        assert!(position.validate().is_ok());
+        assert!(position.remove_piece(Square::B3) == Some((Piece::Cannon, Color::Red)));
+        assert!(position.remove_piece(Square::B3).is_none());
+        assert!(position
+            .colored_pieces(Color::Red, Piece::Cannon)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_position_play_unchecked() {
+        let mut position = Position::empty();
+        position
+            .put_piece(Square::A1, Piece::Rook, Color::Red)
+            .unwrap();
+        position
+            .put_piece(Square::A0, Piece::Rook, Color::Black)
+            .unwrap();
+        position.play_unchecked(Move::new(Square::A1, Square::A5));
+        assert!(position.piece_on(Square::A5) == Some(Piece::Rook));
+        assert!(position.side_to_move() == Color::Black && position.halfmove_clock() == 1);
+        position.play_unchecked(Move::new(Square::A0, Square::A5));
+        assert!(position.colored_pieces(Color::Black, Piece::Rook) == Square::A5.bitboard());
+        assert!(position.colors(Color::Red).is_empty());
+        assert!(position.halfmove_clock() == 0 && position.fullmove_number() == 2);
+    }
+
+    #[test]
+    fn test_position_validate() {
+        let mut position = Position::empty();
+        position.colors[Color::Red as usize] = Square::A1.bitboard();
+        assert!(position.validate() == Err(PositionError::BoardsDisagree(Square::A1.bitboard())));
+        position.pieces[Piece::Rook as usize] = Square::A1.bitboard();
+        position.pieces[Piece::Pawn as usize] = Square::A1.bitboard();
+        assert!(position.validate() == Err(PositionError::PiecesOverlap(Square::A1.bitboard())));
+        position.pieces[Piece::Pawn as usize] = BitBoard::EMPTY;
+        position.colors[Color::Black as usize] = Square::A1.bitboard();
+        assert!(position.validate() == Err(PositionError::ColorsOverlap(Square::A1.bitboard())));
+    }
+}
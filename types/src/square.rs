@@ -174,8 +174,8 @@ mod tests {
         let o1 = square.try_offset(2, 0);
         let o2 = square.try_offset(5, 0);
         let o3 = square.try_offset(0, 2);
-        assert!(o1 == None);
-        assert!(o2 == None);
-        assert!(o3 == None);
+        assert!(o1.is_none());
+        assert!(o2.is_none());
+        assert!(o3.is_none());
     }
 }
@@ -0,0 +1,49 @@
+use types::{BitBoard, Square};
+
+use crate::{
+    generate::ChessMove,
+    king::{BLACK_PALACE, RED_PALACE},
+};
+
+pub const ADVISOR_DELTAS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
+
+pub const ADVISOR_START_RANGE: [Square; 10] = [
+    Square::D1,
+    Square::F1,
+    Square::E2,
+    Square::D3,
+    Square::F3,
+    Square::D8,
+    Square::F8,
+    Square::E9,
+    Square::D0,
+    Square::F0,
+];
+
+// One diagonal step without leaving the palace, whatever stands around.
+pub struct Advisor;
+
+impl ChessMove for Advisor {
+    fn moves(&self, square: Square, _: BitBoard) -> BitBoard {
+        let palace = if RED_PALACE.has(square) {
+            RED_PALACE
+        } else {
+            BLACK_PALACE
+        };
+        let mut moves = BitBoard::EMPTY;
+        for (df, dr) in ADVISOR_DELTAS {
+            if let Some(dst) = square.try_offset(df, dr) {
+                moves |= dst.bitboard();
+            }
+        }
+        moves & palace
+    }
+
+    fn relevant_blockers(&self, _: Square) -> BitBoard {
+        BitBoard::EMPTY
+    }
+
+    fn possible_squares(&self) -> Vec<Square> {
+        Vec::from(ADVISOR_START_RANGE)
+    }
+}
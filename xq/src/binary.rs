@@ -0,0 +1,374 @@
+// Binary table format, all integers little-endian. A file holds one or more
+// sections, each laid out as:
+//
+//   header   MAGIC, version: u32, kind: u32, squares: u32, attacks: u64, hashing: u64
+//   entries  `squares` times: mask: u128, magic: u128, offset: u64, shift: u8, 7 padding bytes
+//   attacks  `attacks` times: BitBoard as u128
+//   checksum FNV-1a 64 of everything above in this section, 8 padding bytes
+//
+// `hashing` is 0 for `Hashing::Wide` and 1 for `Hashing::Folded`; version 1
+// files wrote 0 there as a reserved field. Sections of `TableKind::RookCannon`
+// interleave two attacks per slot, see `RookCannonTable`.
+//
+// Records are multiples of 16 bytes so the attack array stays aligned
+// when the file is mapped into memory.
+
+use types::{BitBoard, Square};
+
+use crate::{
+    generate::{Hashing, MagicEntry},
+    lookup::MagicTable,
+};
+
+pub const MAGIC: [u8; 4] = *b"XQMB";
+pub const VERSION: u32 = 2;
+pub const HEADER_SIZE: usize = 32;
+pub const ENTRY_SIZE: usize = 48;
+pub const ATTACK_SIZE: usize = 16;
+pub const CHECKSUM_SIZE: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableKind {
+    Rook,
+    Cannon,
+    Knight,
+    Bishop,
+    King,
+    Advisor,
+    RedPawn,
+    BlackPawn,
+    KingStep,
+    KnightAttackers,
+    CannonQuiet,
+    RookCannon,
+    RookXray,
+    CannonXray,
+}
+
+impl TableKind {
+    pub const ALL: [Self; 14] = [
+        Self::Rook,
+        Self::Cannon,
+        Self::Knight,
+        Self::Bishop,
+        Self::King,
+        Self::Advisor,
+        Self::RedPawn,
+        Self::BlackPawn,
+        Self::KingStep,
+        Self::KnightAttackers,
+        Self::CannonQuiet,
+        Self::RookCannon,
+        Self::RookXray,
+        Self::CannonXray,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Rook => "rook",
+            Self::Cannon => "cannon",
+            Self::Knight => "knight",
+            Self::Bishop => "bishop",
+            Self::King => "king",
+            Self::Advisor => "advisor",
+            Self::RedPawn => "red_pawn",
+            Self::BlackPawn => "black_pawn",
+            Self::KingStep => "king_step",
+            Self::KnightAttackers => "knight_attackers",
+            Self::CannonQuiet => "cannon_quiet",
+            Self::RookCannon => "rook_cannon",
+            Self::RookXray => "rook_xray",
+            Self::CannonXray => "cannon_xray",
+        }
+    }
+
+    // Attacks stored per slot.
+    pub fn stride(self) -> usize {
+        match self {
+            Self::RookCannon => 2,
+            _ => 1,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    fn try_index(index: u32) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
+}
+
+#[derive(Debug)]
+pub enum BinaryError {
+    BadMagic,
+    UnsupportedVersion(u32),
+    UnknownKind(u32),
+    BadSquareCount(u32),
+    Truncated,
+    Checksum,
+    UnknownHashing(u64),
+    InvalidEntry(Square),
+    MissingKind(TableKind),
+}
+
+pub fn checksum(data: &[u8]) -> u64 {
+    let mut hash = 0xCBF29CE484222325u64;
+    for &byte in data {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001B3);
+    }
+    hash
+}
+
+// Serializes `table` as one section, appending it to `out`.
+pub fn write_table(out: &mut Vec<u8>, kind: TableKind, table: &MagicTable) {
+    let start = out.len();
+    out.extend_from_slice(&MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.extend_from_slice(&(kind as u32).to_le_bytes());
+    out.extend_from_slice(&(table.entries().len() as u32).to_le_bytes());
+    out.extend_from_slice(&(table.attacks().len() as u64).to_le_bytes());
+    let hashing: u64 = match table.hashing() {
+        Hashing::Wide => 0,
+        Hashing::Folded => 1,
+    };
+    out.extend_from_slice(&hashing.to_le_bytes());
+    for entry in table.entries() {
+        out.extend_from_slice(&entry.mask.0.to_le_bytes());
+        out.extend_from_slice(&entry.magic.to_le_bytes());
+        out.extend_from_slice(&(entry.offset as u64).to_le_bytes());
+        out.push(entry.shift);
+        out.extend_from_slice(&[0; 7]);
+    }
+    for attack in table.attacks() {
+        out.extend_from_slice(&attack.0.to_le_bytes());
+    }
+    let sum = checksum(&out[start..]);
+    out.extend_from_slice(&sum.to_le_bytes());
+    out.extend_from_slice(&[0; 8]);
+}
+
+// A validated section borrowed straight from the file bytes, decoding
+// entries and attacks on access.
+#[derive(Clone, Copy)]
+pub struct BinaryTable<'a> {
+    kind: TableKind,
+    hashing: Hashing,
+    entries: &'a [u8],
+    attacks: &'a [u8],
+}
+
+impl<'a> BinaryTable<'a> {
+    // Parses the section at the start of `data`, returning it with the remaining bytes.
+    pub fn parse(data: &'a [u8]) -> Result<(Self, &'a [u8]), BinaryError> {
+        let header = data.get(..HEADER_SIZE).ok_or(BinaryError::Truncated)?;
+        if header[0..4] != MAGIC {
+            return Err(BinaryError::BadMagic);
+        }
+        let version = read_u32(header, 4);
+        if version != VERSION {
+            return Err(BinaryError::UnsupportedVersion(version));
+        }
+        let kind = read_u32(header, 8);
+        let kind = TableKind::try_index(kind).ok_or(BinaryError::UnknownKind(kind))?;
+        let squares = read_u32(header, 12);
+        if squares as usize != Square::NUM {
+            return Err(BinaryError::BadSquareCount(squares));
+        }
+        let attacks_len: usize = read_u64(header, 16)
+            .try_into()
+            .map_err(|_| BinaryError::Truncated)?;
+        let entries_end = HEADER_SIZE + Square::NUM * ENTRY_SIZE;
+        let attacks_end = attacks_len
+            .checked_mul(ATTACK_SIZE)
+            .and_then(|size| size.checked_add(entries_end))
+            .ok_or(BinaryError::Truncated)?;
+        let section_end = attacks_end + CHECKSUM_SIZE;
+        if data.len() < section_end {
+            return Err(BinaryError::Truncated);
+        }
+        if checksum(&data[..attacks_end]) != read_u64(data, attacks_end) {
+            return Err(BinaryError::Checksum);
+        }
+        let hashing = match read_u64(header, 24) {
+            0 => Hashing::Wide,
+            1 => Hashing::Folded,
+            hashing => return Err(BinaryError::UnknownHashing(hashing)),
+        };
+        let table = BinaryTable {
+            kind,
+            hashing,
+            entries: &data[HEADER_SIZE..entries_end],
+            attacks: &data[entries_end..attacks_end],
+        };
+        for square in Square::ALL {
+            let entry = table.entry(square);
+            let valid = hashing.index_bits(entry.shift).is_some_and(|index_bits| {
+                index_bits <= 32
+                    && hashing.valid_magic(entry.mask, entry.magic)
+                    && entry
+                        .offset
+                        .checked_add(kind.stride() << index_bits)
+                        .is_some_and(|end| end <= attacks_len)
+            });
+            if !valid {
+                return Err(BinaryError::InvalidEntry(square));
+            }
+        }
+        Ok((table, &data[section_end..]))
+    }
+
+    pub fn kind(&self) -> TableKind {
+        self.kind
+    }
+
+    pub fn hashing(&self) -> Hashing {
+        self.hashing
+    }
+
+    pub fn entry(&self, square: Square) -> MagicEntry {
+        let record = &self.entries[square as usize * ENTRY_SIZE..][..ENTRY_SIZE];
+        MagicEntry {
+            mask: BitBoard(read_u128(record, 0)),
+            magic: read_u128(record, 16),
+            offset: read_u64(record, 32) as usize,
+            shift: record[40],
+        }
+    }
+
+    pub fn attack(&self, index: usize) -> BitBoard {
+        BitBoard(read_u128(self.attacks, index * ATTACK_SIZE))
+    }
+
+    pub fn attacks_len(&self) -> usize {
+        self.attacks.len() / ATTACK_SIZE
+    }
+
+    // The first attack of the slot, i.e. the rook moves of a `RookCannon` section.
+    pub fn get(&self, square: Square, blockers: BitBoard) -> BitBoard {
+        let entry = self.entry(square);
+        let stride = self.kind.stride();
+        self.attack(entry.offset + stride * self.hashing.index(&entry, blockers))
+    }
+
+    pub fn to_magic_table(&self) -> MagicTable {
+        let entries = Square::ALL.map(|square| self.entry(square));
+        let attacks = (0..self.attacks_len()).map(|i| self.attack(i)).collect();
+        MagicTable::from_parts(self.hashing, entries.to_vec(), attacks)
+    }
+}
+
+// Parses every section of a file.
+pub fn read_tables(mut data: &[u8]) -> Result<Vec<BinaryTable<'_>>, BinaryError> {
+    let mut tables = Vec::new();
+    while !data.is_empty() {
+        let (table, rest) = BinaryTable::parse(data)?;
+        tables.push(table);
+        data = rest;
+    }
+    Ok(tables)
+}
+
+fn read_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+fn read_u64(data: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(data[at..at + 8].try_into().unwrap())
+}
+
+fn read_u128(data: &[u8], at: usize) -> u128 {
+    u128::from_le_bytes(data[at..at + 16].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{knight, rng::Rng};
+
+    fn knight_table(hashing: Hashing) -> MagicTable {
+        MagicTable::search_with(&knight(), hashing, &mut Rng::default())
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        for hashing in [Hashing::Wide, Hashing::Folded] {
+            let table = knight_table(hashing);
+            let mut data = Vec::new();
+            write_table(&mut data, TableKind::Knight, &table);
+            let tables = read_tables(&data).unwrap();
+            assert!(tables.len() == 1 && tables[0].kind() == TableKind::Knight);
+            assert!(tables[0].hashing() == hashing);
+            let blockers = Square::E5.bitboard() | Square::C3.bitboard();
+            for square in Square::ALL {
+                assert!(
+                    tables[0].get(square, blockers) == table.get(square, blockers),
+                    "fail with square = {:?}!",
+                    square
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_binary_rejects_corruption() {
+        let mut data = Vec::new();
+        write_table(&mut data, TableKind::Knight, &knight_table(Hashing::Wide));
+        let mut corrupted = data.clone();
+        corrupted[HEADER_SIZE + 3] ^= 1;
+        assert!(matches!(
+            BinaryTable::parse(&corrupted),
+            Err(BinaryError::Checksum)
+        ));
+        let mut versioned = data.clone();
+        versioned[4] = 1;
+        assert!(matches!(
+            BinaryTable::parse(&versioned),
+            Err(BinaryError::UnsupportedVersion(1))
+        ));
+        assert!(matches!(
+            BinaryTable::parse(&data[..data.len() - 1]),
+            Err(BinaryError::Truncated)
+        ));
+        // An offset near usize::MAX must not wrap around the bounds check.
+        let offset = patch_entry(&data, Square::A1, 32, &u64::MAX.to_le_bytes());
+        assert!(matches!(
+            BinaryTable::parse(&offset),
+            Err(BinaryError::InvalidEntry(Square::A1))
+        ));
+        // A fold of 200 in the high half of a folded magic.
+        let mut data = Vec::new();
+        write_table(&mut data, TableKind::Knight, &knight_table(Hashing::Folded));
+        let folded = patch_entry(&data, Square::A1, 24, &200u64.to_le_bytes());
+        assert!(matches!(
+            BinaryTable::parse(&folded),
+            Err(BinaryError::InvalidEntry(Square::A1))
+        ));
+    }
+
+    // Overwrites bytes of the entry of `square` and fixes up the checksum.
+    fn patch_entry(data: &[u8], square: Square, at: usize, bytes: &[u8]) -> Vec<u8> {
+        let mut data = data.to_vec();
+        let record = HEADER_SIZE + square as usize * ENTRY_SIZE + at;
+        data[record..record + bytes.len()].copy_from_slice(bytes);
+        let attacks_end = data.len() - CHECKSUM_SIZE;
+        let sum = checksum(&data[..attacks_end]);
+        data[attacks_end..attacks_end + 8].copy_from_slice(&sum.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_binary_alignment() {
+        let table = knight_table(Hashing::Wide);
+        let mut data = Vec::new();
+        write_table(&mut data, TableKind::Knight, &table);
+        write_table(&mut data, TableKind::Knight, &table);
+        let tables = read_tables(&data).unwrap();
+        assert!(tables.len() == 2);
+        for table in tables {
+            let attacks = table.attacks.as_ptr() as usize - data.as_ptr() as usize;
+            assert!(attacks.is_multiple_of(16));
+        }
+    }
+}
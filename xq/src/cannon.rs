@@ -14,43 +14,89 @@ impl CannonAttack {
     }
 }
 
+// Captures over exactly one screen. The target may stand on the edge, so
+// edge squares are relevant blockers of every ray long enough to hold both a
+// screen and a target.
 impl ChessMove for CannonAttack {
     fn moves(&self, square: Square, blockers: BitBoard) -> BitBoard {
         let mut moves = BitBoard::EMPTY;
         for (df, dr) in self.deltas {
+            let mut screened = false;
             let mut ray = square;
-            while !blockers.has(ray) {
-                if let Some(shifted) = ray.try_offset(df, dr) {
-                    ray = shifted;
-                } else {
-                    break;
-                }
-            }
-            if let Some(mut ray) = ray.try_offset(df, dr) {
-                while !blockers.has(ray) {
-                    if let Some(shifted) = ray.try_offset(df, dr) {
-                        ray = shifted;
-                    } else {
+            while let Some(shifted) = ray.try_offset(df, dr) {
+                ray = shifted;
+                if blockers.has(ray) {
+                    if screened {
+                        moves |= ray.bitboard();
                         break;
                     }
+                    screened = true;
                 }
-                moves |= ray.bitboard();
             }
         }
         moves
     }
 
     fn relevant_blockers(&self, square: Square) -> BitBoard {
-        let mut blockers = BitBoard::EMPTY;
+        ray_blockers(&self.deltas, square, 2)
+    }
+
+    fn possible_squares(&self) -> Vec<Square> {
+        Vec::from(Square::ALL)
+    }
+}
+
+// Squares of the rays from `square` holding at least `min_len` squares.
+// Unlike a rook, a cannon depends on whether the edge square is occupied.
+pub(crate) fn ray_blockers(deltas: &[(i8, i8); 4], square: Square, min_len: usize) -> BitBoard {
+    let mut blockers = BitBoard::EMPTY;
+    for &(df, dr) in deltas {
+        let mut ray = BitBoard::EMPTY;
+        let mut len = 0;
+        let mut next = square;
+        while let Some(shifted) = next.try_offset(df, dr) {
+            ray |= shifted.bitboard();
+            len += 1;
+            next = shifted;
+        }
+        if len >= min_len {
+            blockers |= ray;
+        }
+    }
+    blockers
+}
+
+// Slides up to the first blocker, which may be the edge square.
+pub struct CannonQuiet {
+    deltas: [(i8, i8); 4],
+}
+
+impl CannonQuiet {
+    pub fn new() -> Self {
+        CannonQuiet {
+            deltas: SLIDER_ONE_STEP,
+        }
+    }
+}
+
+impl ChessMove for CannonQuiet {
+    fn moves(&self, square: Square, blockers: BitBoard) -> BitBoard {
+        let mut moves = BitBoard::EMPTY;
         for (df, dr) in self.deltas {
             let mut ray = square;
             while let Some(shifted) = ray.try_offset(df, dr) {
-                blockers |= ray.bitboard();
+                if blockers.has(shifted) {
+                    break;
+                }
                 ray = shifted;
+                moves |= ray.bitboard();
             }
         }
-        blockers &= !square.bitboard();
-        blockers
+        moves
+    }
+
+    fn relevant_blockers(&self, square: Square) -> BitBoard {
+        ray_blockers(&self.deltas, square, 1)
     }
 
     fn possible_squares(&self) -> Vec<Square> {
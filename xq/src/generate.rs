@@ -1,6 +1,9 @@
 use crate::rng::Rng;
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::{BTreeMap, HashSet},
+    time::Instant,
+};
 use types::{BitBoard, Square};
 
 pub trait ChessMove {
@@ -9,25 +12,203 @@ pub trait ChessMove {
     fn possible_squares(&self) -> Vec<Square>;
 }
 
+#[derive(Clone, Copy, Debug)]
 pub struct MagicEntry {
     pub mask: BitBoard,
     pub magic: u128,
     pub shift: u8,
+    pub offset: usize,
 }
 
-#[derive(Serialize, Deserialize)]
+// How the masked blockers are turned into an index.
+//
+// `Wide` multiplies the whole 128-bit board by the magic. `Folded` first folds
+// the board into a u64 as `low ^ (high << fold)`, then does a single 64-bit
+// multiply. The low half of `magic` holds the 64-bit multiplier and the high
+// half the `fold` shift, chosen per square so no two mask bits overlap.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Hashing {
+    #[default]
+    Wide,
+    Folded,
+}
+
+impl Hashing {
+    const fn hash_bits(self) -> u8 {
+        match self {
+            Self::Wide => 128,
+            Self::Folded => 64,
+        }
+    }
+
+    pub const fn shift(self, index_bits: u8) -> u8 {
+        self.hash_bits() - index_bits
+    }
+
+    pub fn index_bits(self, shift: u8) -> Option<u8> {
+        self.hash_bits().checked_sub(shift)
+    }
+
+    // Whether some magic can index `mask`: folding needs a shift keeping the
+    // mask bits apart, which the cannon masks of some squares lack.
+    pub fn supports(self, mask: BitBoard) -> bool {
+        match self {
+            Self::Wide => true,
+            Self::Folded => fold_shift(mask).is_some(),
+        }
+    }
+
+    // Whether `magic` can index `mask`: a `Folded` magic must carry a fold
+    // below 64 that keeps the mask bits apart.
+    pub fn valid_magic(self, mask: BitBoard, magic: u128) -> bool {
+        match self {
+            Self::Wide => true,
+            Self::Folded => {
+                let (low, high) = (mask.0 as u64, (mask.0 >> 64) as u64);
+                let fold = magic >> 64;
+                fold < 64 && low & (high << fold) == 0
+            }
+        }
+    }
+
+    pub fn index(self, entry: &MagicEntry, blockers: BitBoard) -> usize {
+        match self {
+            Self::Wide => magic_index(entry, blockers),
+            Self::Folded => folded_magic_index(entry, blockers),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MagicEntryGen {
     pub square: Square,
     pub magic: String,
     pub shift: u8,
     pub size: usize,
+    // Start of this square's segment in the concatenated attack table.
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default)]
+    pub hashing: Hashing,
+}
+
+impl MagicEntryGen {
+    // Parses the hex string written by `find_magic` back into the magic number.
+    pub fn parse_magic(&self) -> Option<u128> {
+        parse_hex(&self.magic)
+    }
+}
+
+// A `magic-gen` output value: either the magics of a piece or,
+// when requested, its complete concatenated attack table.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TableGen {
+    Magics(Vec<MagicEntryGen>),
+    Attacks(Vec<String>),
+}
+
+pub type TablesGen = BTreeMap<String, TableGen>;
+
+pub fn format_hex(value: u128) -> String {
+    format!("0x{:032x}", value)
+}
+
+pub fn parse_hex(value: &str) -> Option<u128> {
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    u128::from_str_radix(digits, 16).ok()
+}
+
+// Sorts the entries by square and lays their tables out back to back,
+// filling in each entry's offset into the returned contiguous table.
+pub fn concat_tables(
+    mut tables: Vec<(MagicEntryGen, Vec<BitBoard>)>,
+) -> (Vec<MagicEntryGen>, Vec<BitBoard>) {
+    tables.sort_by_key(|(entry, _)| entry.square);
+    let mut entries = Vec::with_capacity(tables.len());
+    let mut attacks = Vec::new();
+    for (mut entry, table) in tables {
+        entry.offset = attacks.len();
+        attacks.extend(table);
+        entries.push(entry);
+    }
+    (entries, attacks)
+}
+
+// Lays sparse tables out in one shared array, letting a table start inside
+// another as long as every slot both of them use holds the same move set.
+// Meant for tables sharing one shift, where most slots of a square are unused.
+pub fn pack_tables(
+    mut tables: Vec<(MagicEntryGen, Vec<Option<BitBoard>>)>,
+) -> (Vec<MagicEntryGen>, Vec<BitBoard>) {
+    // The fullest tables are the hardest to fit into gaps, so place them first.
+    tables.sort_by_key(|(entry, table)| {
+        (
+            std::cmp::Reverse(table.iter().flatten().count()),
+            entry.square,
+        )
+    });
+    let mut shared: Vec<Option<BitBoard>> = Vec::new();
+    let mut entries = Vec::with_capacity(tables.len());
+    for (mut entry, table) in tables {
+        let used: Vec<_> = table
+            .iter()
+            .enumerate()
+            .filter_map(|(i, moves)| moves.map(|moves| (i, moves)))
+            .collect();
+        // Any offset at the end of the shared array fits, so this always succeeds.
+        let offset = (0..=shared.len())
+            .find(|&offset| {
+                used.iter().all(|&(i, moves)| match shared.get(offset + i) {
+                    Some(Some(stored)) => *stored == moves,
+                    _ => true,
+                })
+            })
+            .unwrap();
+        if shared.len() < offset + table.len() {
+            shared.resize(offset + table.len(), None);
+        }
+        for (i, moves) in used {
+            shared[offset + i] = Some(moves);
+        }
+        entry.offset = offset;
+        entries.push(entry);
+    }
+    entries.sort_by_key(|entry| entry.square);
+    let attacks = shared
+        .into_iter()
+        .map(|moves| moves.unwrap_or(BitBoard::EMPTY))
+        .collect();
+    (entries, attacks)
 }
 
 pub fn magic_index(entry: &MagicEntry, blockers: BitBoard) -> usize {
     let blockers = blockers & entry.mask;
-    let hash = blockers.0.wrapping_mul(entry.magic as u128);
-    let index = (hash.wrapping_shr(entry.shift.into())) as usize;
-    index
+    let hash = blockers.0.wrapping_mul(entry.magic);
+    hash.wrapping_shr(entry.shift.into()) as usize
+}
+
+pub fn folded_magic_index(entry: &MagicEntry, blockers: BitBoard) -> usize {
+    let blockers = (blockers & entry.mask).0;
+    let folded = blockers as u64 ^ ((blockers >> 64) as u64) << (entry.magic >> 64);
+    let hash = folded.wrapping_mul(entry.magic as u64);
+    hash.wrapping_shr(entry.shift.into()) as usize
+}
+
+// Smallest shift of the high half that keeps every bit of `mask` apart
+// when folded, so the fold loses no blocker information.
+pub fn fold_shift(mask: BitBoard) -> Option<u32> {
+    let low = mask.0 as u64;
+    let high = (mask.0 >> 64) as u64;
+    (0..=high.leading_zeros()).find(|&fold| low & (high << fold) == 0)
+}
+
+// Relevant blockers of a square shared by `pieces`: the union of their
+// masks, so that every piece's moves are determined by the index.
+pub fn shared_mask(pieces: &[&dyn ChessMove], square: Square) -> BitBoard {
+    pieces.iter().fold(BitBoard::EMPTY, |mask, piece| {
+        mask | piece.relevant_blockers(square)
+    })
 }
 
 // Given a sliding piece and a square, finds a magic number that
@@ -36,54 +217,162 @@ pub fn find_magic(
     slider: &dyn ChessMove,
     square: Square,
     index_bits: u8,
-    rng: Arc<Mutex<Rng>>,
+    hashing: Hashing,
+    rng: &mut Rng,
 ) -> (MagicEntryGen, Vec<BitBoard>) {
-    let mask = slider.relevant_blockers(square);
-    let shift = 128 - index_bits;
-    loop {
+    find_shared_magic(&[slider], square, index_bits, hashing, rng)
+}
+
+// Like `find_magic`, but gives up once `deadline` has passed.
+pub fn find_magic_before(
+    slider: &dyn ChessMove,
+    square: Square,
+    index_bits: u8,
+    hashing: Hashing,
+    rng: &mut Rng,
+    deadline: Instant,
+) -> Option<(MagicEntryGen, Vec<BitBoard>)> {
+    find_shared_magic_before(&[slider], square, index_bits, hashing, rng, deadline)
+}
+
+// Finds one magic valid for all `pieces` over their `shared_mask`, and returns
+// their tables interleaved as by `try_make_shared_table`. Panics unless
+// `hashing` supports the mask.
+// The entry's `size` counts slots, each holding one move set per piece.
+pub fn find_shared_magic(
+    pieces: &[&dyn ChessMove],
+    square: Square,
+    index_bits: u8,
+    hashing: Hashing,
+    rng: &mut Rng,
+) -> (MagicEntryGen, Vec<BitBoard>) {
+    search_magic(pieces, square, index_bits, hashing, rng, || false).unwrap()
+}
+
+// Like `find_shared_magic`, but gives up once `deadline` has passed.
+pub fn find_shared_magic_before(
+    pieces: &[&dyn ChessMove],
+    square: Square,
+    index_bits: u8,
+    hashing: Hashing,
+    rng: &mut Rng,
+    deadline: Instant,
+) -> Option<(MagicEntryGen, Vec<BitBoard>)> {
+    search_magic(pieces, square, index_bits, hashing, rng, || {
+        Instant::now() >= deadline
+    })
+}
+
+fn search_magic(
+    pieces: &[&dyn ChessMove],
+    square: Square,
+    index_bits: u8,
+    hashing: Hashing,
+    rng: &mut Rng,
+    give_up: impl Fn() -> bool,
+) -> Option<(MagicEntryGen, Vec<BitBoard>)> {
+    let mask = shared_mask(pieces, square);
+    if !hashing.supports(mask) {
+        return None;
+    }
+    let shift = hashing.shift(index_bits);
+    let fold = (fold_shift(mask).unwrap_or_default() as u128) << 64;
+    while !give_up() {
         // Magics require a low number of active bits, so we AND
         // by two more random values to cut down on the bits set.
-        let magic = {
-            let mut rng = rng.lock().unwrap();
-            rng.next_u128() & rng.next_u128() & rng.next_u128()
+        let magic = match hashing {
+            Hashing::Wide => rng.next_u128() & rng.next_u128() & rng.next_u128(),
+            Hashing::Folded => fold | (rng.next_u64() & rng.next_u64() & rng.next_u64()) as u128,
         };
-        let magic_entry = MagicEntry { mask, magic, shift };
-        if let Ok(table) = try_make_table(slider, square, &magic_entry) {
-            let magic = format!("0x{:032x}", magic);
+        let magic_entry = MagicEntry {
+            mask,
+            magic,
+            shift,
+            offset: 0,
+        };
+        if let Ok(table) = try_make_shared_table(pieces, square, &magic_entry, hashing) {
             let magic_entry_gen = MagicEntryGen {
                 square,
-                magic,
+                magic: format_hex(magic),
                 shift,
-                size: table.len(),
+                size: table.len() / pieces.len(),
+                offset: 0,
+                hashing,
             };
-            return (magic_entry_gen, table);
+            return Some((magic_entry_gen, table));
         }
     }
+    None
 }
 
-struct TableFillError;
+#[derive(Debug)]
+pub struct TableFillError;
 
 // Attempt to fill in a hash table using a magic number.
 // Fails if there are any non-constructive collisions.
-fn try_make_table(
+pub fn try_make_table(
     slider: &dyn ChessMove,
     square: Square,
     magic_entry: &MagicEntry,
+    hashing: Hashing,
+) -> Result<Vec<BitBoard>, TableFillError> {
+    try_make_shared_table(&[slider], square, magic_entry, hashing)
+}
+
+// Fills the tables of several pieces sharing one mask and magic, storing the
+// moves of `pieces[k]` for slot `i` at `i * pieces.len() + k`, so all move
+// sets of one blocker configuration sit next to each other.
+pub fn try_make_shared_table(
+    pieces: &[&dyn ChessMove],
+    square: Square,
+    magic_entry: &MagicEntry,
+    hashing: Hashing,
 ) -> Result<Vec<BitBoard>, TableFillError> {
-    let index_bits = 128 - magic_entry.shift;
-    let mut table = vec![BitBoard::EMPTY; 1 << index_bits];
+    let table = try_make_sparse_shared_table(pieces, square, magic_entry, hashing)?;
+    Ok(table
+        .into_iter()
+        .map(|moves| moves.unwrap_or(BitBoard::EMPTY))
+        .collect())
+}
+
+// Same as `try_make_table`, but slots that no blocker configuration
+// maps to are left as `None`.
+pub fn try_make_sparse_table(
+    slider: &dyn ChessMove,
+    square: Square,
+    magic_entry: &MagicEntry,
+    hashing: Hashing,
+) -> Result<Vec<Option<BitBoard>>, TableFillError> {
+    try_make_sparse_shared_table(&[slider], square, magic_entry, hashing)
+}
+
+// Same as `try_make_shared_table`, but unused slots are left as `None`.
+pub fn try_make_sparse_shared_table(
+    pieces: &[&dyn ChessMove],
+    square: Square,
+    magic_entry: &MagicEntry,
+    hashing: Hashing,
+) -> Result<Vec<Option<BitBoard>>, TableFillError> {
+    let stride = pieces.len();
+    let index_bits = hashing
+        .index_bits(magic_entry.shift)
+        .ok_or(TableFillError)?;
+    // Slots are tracked as `Option` because EMPTY is a valid move set,
+    // e.g. a king with no forbidden squares, and must not be overwritten.
+    let mut table = vec![None; stride << index_bits];
     // Iterate all configurations of blockers
     let mut blockers = BitBoard::EMPTY;
     loop {
-        let moves = slider.moves(square, blockers);
-        let index = magic_index(magic_entry, blockers);
-        let table_entry = &mut table[index];
-        if table_entry.is_empty() {
-            // Write to empty slot
-            *table_entry = moves;
-        } else if *table_entry != moves {
-            // Having two different move sets in the same slot is a hash collision
-            return Err(TableFillError);
+        let index = hashing.index(magic_entry, blockers) * stride;
+        for (slot, piece) in table[index..index + stride].iter_mut().zip(pieces) {
+            let moves = piece.moves(square, blockers);
+            match *slot {
+                // Write to empty slot
+                None => *slot = Some(moves),
+                // Having two different move sets in the same slot is a hash collision
+                Some(table_entry) if table_entry != moves => return Err(TableFillError),
+                Some(_) => {}
+            }
         }
 
         // Carry-Rippler trick that enumerates all subsets of the mask, getting us all blockers.
@@ -96,3 +385,113 @@ fn try_make_table(
     }
     Ok(table)
 }
+
+// Fewest index bits that can possibly work: every distinct move set
+// of the square needs its own slot.
+pub fn min_index_bits(slider: &dyn ChessMove, square: Square) -> u8 {
+    let mask = slider.relevant_blockers(square);
+    let mut move_sets = HashSet::new();
+    let mut blockers = BitBoard::EMPTY;
+    loop {
+        move_sets.insert(slider.moves(square, blockers));
+        blockers.0 = blockers.0.wrapping_sub(mask.0) & mask.0;
+        if blockers.is_empty() {
+            break;
+        }
+    }
+    move_sets.len().next_power_of_two().trailing_zeros() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Moves to its own square once anything blocks it, so the empty
+    // blocker set is the only one without moves.
+    struct Stub;
+
+    impl ChessMove for Stub {
+        fn moves(&self, square: Square, blockers: BitBoard) -> BitBoard {
+            if blockers.is_empty() {
+                BitBoard::EMPTY
+            } else {
+                square.bitboard()
+            }
+        }
+
+        fn relevant_blockers(&self, _: Square) -> BitBoard {
+            Square::A2.bitboard() | Square::B2.bitboard()
+        }
+
+        fn possible_squares(&self) -> Vec<Square> {
+            vec![Square::A1]
+        }
+    }
+
+    #[test]
+    fn test_try_make_table_empty_moves_collision() {
+        let entry = MagicEntry {
+            mask: Stub.relevant_blockers(Square::A1),
+            magic: 0,
+            shift: 127,
+            offset: 0,
+        };
+        assert!(try_make_table(&Stub, Square::A1, &entry, Hashing::Wide).is_err());
+    }
+
+    #[test]
+    fn test_pack_tables_overlap() {
+        let knight = crate::knight();
+        let mut rng = Rng::default();
+        let tables = [Square::E5, Square::E6]
+            .map(|square| {
+                let (entry, _) = find_magic(&knight, square, 4, Hashing::Wide, &mut rng);
+                let magic_entry = MagicEntry {
+                    mask: knight.relevant_blockers(square),
+                    magic: entry.parse_magic().unwrap(),
+                    shift: entry.shift,
+                    offset: 0,
+                };
+                let table =
+                    try_make_sparse_table(&knight, square, &magic_entry, Hashing::Wide).unwrap();
+                (entry, table)
+            })
+            .to_vec();
+        let (entries, attacks) = pack_tables(tables);
+        assert!(attacks.len() <= 32);
+        for entry in entries {
+            let magic_entry = MagicEntry {
+                mask: knight.relevant_blockers(entry.square),
+                magic: entry.parse_magic().unwrap(),
+                shift: entry.shift,
+                offset: entry.offset,
+            };
+            let mut blockers = BitBoard::EMPTY;
+            loop {
+                let index = magic_entry.offset + magic_index(&magic_entry, blockers);
+                assert!(attacks[index] == knight.moves(entry.square, blockers));
+                blockers.0 = blockers.0.wrapping_sub(magic_entry.mask.0) & magic_entry.mask.0;
+                if blockers.is_empty() {
+                    break;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_fold_shift() {
+        for piece in [&crate::rook() as &dyn ChessMove, &crate::king()] {
+            for square in piece.possible_squares() {
+                let mask = piece.relevant_blockers(square);
+                let fold = fold_shift(mask).unwrap();
+                let folded = mask.0 as u64 | ((mask.0 >> 64) as u64) << fold;
+                assert!(folded.count_ones() == mask.popcnt());
+            }
+        }
+    }
+
+    #[test]
+    fn test_min_index_bits() {
+        assert!(min_index_bits(&Stub, Square::A1) == 1);
+    }
+}
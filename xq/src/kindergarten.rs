@@ -0,0 +1,130 @@
+use types::{BitBoard, File, Rank, Square};
+
+use crate::generate::ChessMove;
+
+const RANK_1: u128 = 0x1FF;
+
+// Bits of file A on the eight inner ranks.
+const FILE_A_INNER: u128 = {
+    let mut bits = 0;
+    let mut rank = 1;
+    while rank < 9 {
+        bits |= 1 << (rank * 9);
+        rank += 1;
+    }
+    bits
+};
+
+const FILE_A: u128 = FILE_A_INNER | 1 | (1 << 81);
+
+// Moves the bit of inner rank `r` to bit `100 + r`. Every partial product
+// lands on a different bit, so the multiplication never carries.
+const FILE_GATHER: u128 = {
+    let mut magic = 0;
+    let mut rank = 1;
+    while rank < 9 {
+        magic |= 1 << (100 - 8 * rank);
+        rank += 1;
+    }
+    magic
+};
+
+// Kindergarten attack tables for rook-like pieces: attacks along a rank
+// only depend on the nine squares of the rank and attacks along a file on
+// the ten squares of the file, so both are looked up directly by occupancy
+// without a magic per square. The edge squares are kept in the index since
+// cannons depend on them.
+pub struct KindergartenTable {
+    ranks: Box<[[u16; 512]; File::NUM]>,
+    files: Box<[[u128; 1024]; Rank::NUM]>,
+}
+
+impl KindergartenTable {
+    // Fills the tables from `piece`, which must only move along ranks and files.
+    pub fn new(piece: &dyn ChessMove) -> Self {
+        let mut ranks = Box::new([[0; 512]; File::NUM]);
+        for file in File::ALL {
+            let square = Square::new(file, Rank::First);
+            for occupancy in 0..512 {
+                let blockers = BitBoard(occupancy as u128) & !square.bitboard();
+                let moves = piece.moves(square, blockers).0 & RANK_1;
+                ranks[file as usize][occupancy] = moves as u16;
+            }
+        }
+        let mut files = Box::new([[0; 1024]; Rank::NUM]);
+        for rank in Rank::ALL {
+            let square = Square::new(File::A, rank);
+            for occupancy in 0..1024 {
+                // Inner ranks in bits 0..8, then the first and the last rank.
+                let mut blockers = BitBoard::EMPTY;
+                for inner in 0..8 {
+                    if occupancy & (1 << inner) != 0 {
+                        blockers.0 |= 1 << ((inner + 1) * 9);
+                    }
+                }
+                if occupancy & (1 << 8) != 0 {
+                    blockers.0 |= 1;
+                }
+                if occupancy & (1 << 9) != 0 {
+                    blockers.0 |= 1 << 81;
+                }
+                blockers &= !square.bitboard();
+                files[rank as usize][occupancy] = piece.moves(square, blockers).0 & FILE_A;
+            }
+        }
+        KindergartenTable { ranks, files }
+    }
+
+    pub fn get(&self, square: Square, blockers: BitBoard) -> BitBoard {
+        let (file, rank) = (square.file() as usize, square.rank() as usize);
+        let rank_index = (blockers.0 >> (rank * 9)) as usize & 0x1FF;
+        let file_bits = blockers.0 >> file;
+        let inner = ((file_bits & FILE_A_INNER).wrapping_mul(FILE_GATHER) >> 101) as usize & 0xFF;
+        let edges = (file_bits & 1) << 8 | (file_bits >> 81 & 1) << 9;
+        let file_index = inner | edges as usize;
+        let rank_moves = (self.ranks[file][rank_index] as u128) << (rank * 9);
+        let file_moves = self.files[rank][file_index] << file;
+        BitBoard(rank_moves | file_moves)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cannon, cannon_quiet, cannon_xray, rng::Rng, rook, rook_xray};
+
+    fn check(piece: &dyn ChessMove) {
+        let table = KindergartenTable::new(piece);
+        let mut rng = Rng::default();
+        for _ in 0..200 {
+            let blockers = BitBoard(rng.next_u128() & rng.next_u128() & ((1 << 90) - 1));
+            for square in Square::ALL {
+                let blockers = blockers | square.bitboard();
+                assert!(
+                    table.get(square, blockers)
+                        == piece.moves(square, blockers & !square.bitboard()),
+                    "fail with square = {:?}, blockers = {:?}!",
+                    square,
+                    blockers
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_kindergarten_rook() {
+        check(&rook());
+    }
+
+    #[test]
+    fn test_kindergarten_cannon() {
+        check(&cannon());
+        check(&cannon_quiet());
+    }
+
+    #[test]
+    fn test_kindergarten_xray() {
+        check(&rook_xray());
+        check(&cannon_xray());
+    }
+}
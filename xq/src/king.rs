@@ -76,3 +76,34 @@ impl King {
         King {}
     }
 }
+
+pub const KING_STEP_DELTAS: [(i8, i8); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+// One orthogonal step without leaving the palace, whatever stands around.
+// Removing the squares forbidden by the other king gives the king's moves.
+pub struct KingStep;
+
+impl ChessMove for KingStep {
+    fn moves(&self, square: Square, _: BitBoard) -> BitBoard {
+        let palace = if RED_PALACE.has(square) {
+            RED_PALACE
+        } else {
+            BLACK_PALACE
+        };
+        let mut moves = BitBoard::EMPTY;
+        for (df, dr) in KING_STEP_DELTAS {
+            if let Some(dst) = square.try_offset(df, dr) {
+                moves |= dst.bitboard();
+            }
+        }
+        moves & palace
+    }
+
+    fn relevant_blockers(&self, _: Square) -> BitBoard {
+        BitBoard::EMPTY
+    }
+
+    fn possible_squares(&self) -> Vec<Square> {
+        (RED_PALACE | BLACK_PALACE).into_iter().collect()
+    }
+}
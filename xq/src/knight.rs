@@ -5,20 +5,37 @@ pub struct LameLeaper<const N: usize> {
     deltas: [(i8, i8); N],
     lamels: [(i8, i8); N],
     start_range: Vec<Square>,
+    // A piece on a square of one mask only lands inside that mask.
+    destinations: Vec<BitBoard>,
 }
 
 impl<const N: usize> LameLeaper<N> {
-    pub fn new(deltas: [(i8, i8); N], lamels: [(i8, i8); N], start_range: Vec<Square>) -> Self {
+    pub fn new(
+        deltas: [(i8, i8); N],
+        lamels: [(i8, i8); N],
+        start_range: Vec<Square>,
+        destinations: Vec<BitBoard>,
+    ) -> Self {
         LameLeaper {
             deltas,
             lamels,
             start_range,
+            destinations,
         }
     }
+
+    fn destination(&self, square: Square) -> BitBoard {
+        self.destinations
+            .iter()
+            .copied()
+            .find(|mask| mask.has(square))
+            .unwrap_or(BitBoard::EMPTY)
+    }
 }
 
 impl<const N: usize> ChessMove for LameLeaper<N> {
     fn moves(&self, square: types::Square, blockers: types::BitBoard) -> types::BitBoard {
+        let destination = self.destination(square);
         let mut moves = BitBoard::EMPTY;
         for i in 0..N {
             if let (Some(lamel), Some(dst)) = (
@@ -30,14 +47,21 @@ impl<const N: usize> ChessMove for LameLeaper<N> {
                 }
             }
         }
-        moves
+        moves & destination
     }
 
+    // Only the legs of moves that can land somewhere.
     fn relevant_blockers(&self, square: types::Square) -> types::BitBoard {
+        let destination = self.destination(square);
         let mut blockers = BitBoard::EMPTY;
-        for lamel in self.lamels {
-            if let Some(lamel) = square.try_offset(lamel.0, lamel.1) {
-                blockers |= lamel.bitboard();
+        for i in 0..N {
+            if let (Some(lamel), Some(dst)) = (
+                square.try_offset(self.lamels[i].0, self.lamels[i].1),
+                square.try_offset(self.deltas[i].0, self.deltas[i].1),
+            ) {
+                if destination.has(dst) {
+                    blockers |= lamel.bitboard();
+                }
             }
         }
         blockers
@@ -70,6 +94,34 @@ pub const KNIGHT_LAMELS: [(i8, i8); 8] = [
     (0, -1),
 ];
 
+// Seen from the attacked square: a knight `KNIGHT_DELTAS[i]` away attacks it
+// unless the leg diagonally next to the attacked square is blocked.
+pub const KNIGHT_ATTACKER_DELTAS: [(i8, i8); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (1, -2),
+    (2, -1),
+    (2, 1),
+    (-1, 2),
+    (1, 2),
+];
+
+pub const KNIGHT_ATTACKER_LAMELS: [(i8, i8); 8] = [
+    (-1, -1),
+    (-1, 1),
+    (-1, -1),
+    (1, -1),
+    (1, -1),
+    (1, 1),
+    (-1, 1),
+    (1, 1),
+];
+
+// Ranks 1 to 5 and 6 to 10, the two sides of the river.
+pub const RED_HALF: BitBoard = BitBoard((1 << 45) - 1);
+pub const BLACK_HALF: BitBoard = BitBoard(BitBoard::FULL.0 ^ RED_HALF.0);
+
 pub const BISHOP_DELTAS: [(i8, i8); 4] = [(2, 2), (2, -2), (-2, 2), (-2, -2)];
 
 pub const BISHOP_LAMELS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, 1), (-1, -1)];
@@ -90,3 +142,66 @@ pub const BISHOP_START_RANGE: [Square; 14] = [
     Square::C0,
     Square::G0,
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bishop, knight, knight_attackers, rng::Rng};
+
+    #[test]
+    fn test_bishop_stays_home() {
+        let bishop = bishop();
+        for square in BISHOP_START_RANGE {
+            let home = if RED_HALF.has(square) {
+                RED_HALF
+            } else {
+                BLACK_HALF
+            };
+            let mask = bishop.relevant_blockers(square);
+            let mut blockers = BitBoard::EMPTY;
+            loop {
+                let moves = bishop.moves(square, blockers);
+                assert!(
+                    (moves & !home).is_empty(),
+                    "fail with square = {:?}, blockers = {:?}!",
+                    square,
+                    blockers
+                );
+                blockers.0 = blockers.0.wrapping_sub(mask.0) & mask.0;
+                if blockers.is_empty() {
+                    break;
+                }
+            }
+            assert!((mask & !home).is_empty());
+        }
+        assert!(
+            bishop.moves(Square::C5, BitBoard::EMPTY)
+                == Square::A3.bitboard() | Square::E3.bitboard()
+        );
+        assert!(
+            bishop.moves(Square::G6, BitBoard::EMPTY)
+                == Square::E8.bitboard() | Square::I8.bitboard()
+        );
+    }
+
+    #[test]
+    fn test_knight_attackers() {
+        let (knight, attackers) = (knight(), knight_attackers());
+        let mut rng = Rng::default();
+        for _ in 0..20 {
+            let blockers = BitBoard(rng.next_u128() & rng.next_u128() & ((1 << 90) - 1));
+            for target in Square::ALL {
+                let expected = Square::ALL
+                    .into_iter()
+                    .filter(|&from| knight.moves(from, blockers).has(target))
+                    .fold(BitBoard::EMPTY, |board, from| board | from.bitboard());
+                assert!(
+                    attackers.moves(target, blockers) == expected,
+                    "fail with square = {:?}, blockers = {:?}!",
+                    target,
+                    blockers
+                );
+            }
+        }
+    }
+}
@@ -1,14 +1,25 @@
+mod advisor;
+pub mod binary;
 mod cannon;
 pub mod generate;
+pub mod kindergarten;
 mod king;
 mod knight;
+pub mod lookup;
+pub mod movegen;
+mod pawn;
+pub mod perft;
 pub mod rng;
 mod rook;
+mod xray;
 
+use advisor::Advisor;
 use cannon::*;
-use king::King;
+use king::{King, KingStep};
 use knight::*;
+use pawn::Pawn;
 use rook::*;
+use xray::{CannonXray, RookXray};
 
 pub fn rook() -> Slider {
     Slider::new(SLIDER_ONE_STEP, Vec::from(types::Square::ALL))
@@ -18,15 +29,65 @@ pub fn cannon() -> CannonAttack {
     CannonAttack::new()
 }
 
+pub fn cannon_quiet() -> CannonQuiet {
+    CannonQuiet::new()
+}
+
+// Squares a rook attacks through one blocker.
+pub fn rook_xray() -> RookXray {
+    RookXray::new()
+}
+
+// Pieces a cannon captures over two screens.
+pub fn cannon_xray() -> CannonXray {
+    CannonXray::new()
+}
+
 pub fn knight() -> LameLeaper<8> {
-    LameLeaper::new(KNIGHT_DELTAS, KNIGHT_LAMELS, Vec::from(types::Square::ALL))
+    LameLeaper::new(
+        KNIGHT_DELTAS,
+        KNIGHT_LAMELS,
+        Vec::from(types::Square::ALL),
+        vec![types::BitBoard::FULL],
+    )
+}
+
+// Squares a knight attacks the square from.
+pub fn knight_attackers() -> LameLeaper<8> {
+    LameLeaper::new(
+        KNIGHT_ATTACKER_DELTAS,
+        KNIGHT_ATTACKER_LAMELS,
+        Vec::from(types::Square::ALL),
+        vec![types::BitBoard::FULL],
+    )
 }
 
 pub fn bishop() -> LameLeaper<4> {
     let start_range = Vec::from(BISHOP_START_RANGE);
-    LameLeaper::new(BISHOP_DELTAS, BISHOP_LAMELS, start_range)
+    LameLeaper::new(
+        BISHOP_DELTAS,
+        BISHOP_LAMELS,
+        start_range,
+        vec![RED_HALF, BLACK_HALF],
+    )
 }
 
 pub fn king() -> King {
     King::new()
 }
+
+pub fn king_step() -> KingStep {
+    KingStep
+}
+
+pub fn advisor() -> Advisor {
+    Advisor
+}
+
+pub fn red_pawn() -> Pawn {
+    Pawn::new(types::Color::Red)
+}
+
+pub fn black_pawn() -> Pawn {
+    Pawn::new(types::Color::Black)
+}
@@ -0,0 +1,969 @@
+use std::borrow::Cow;
+
+use types::{BitBoard, Color, Piece, Square};
+
+use crate::{
+    advisor,
+    binary::{read_tables, BinaryError, BinaryTable, TableKind},
+    bishop, black_pawn, cannon, cannon_quiet, cannon_xray,
+    generate::{
+        concat_tables, find_magic, parse_hex, shared_mask, try_make_shared_table, try_make_table,
+        ChessMove, Hashing, MagicEntry, MagicEntryGen, TableGen, TablesGen,
+    },
+    kindergarten::KindergartenTable,
+    king, king_step, knight, knight_attackers, red_pawn,
+    rng::Rng,
+    rook, rook_xray,
+};
+
+#[derive(Debug)]
+pub enum LoadError {
+    MissingTable(String),
+    InvalidTable(String),
+    // A square the piece can stand on has no magic.
+    MissingSquare(Square),
+    DuplicateSquare(Square),
+    InvalidMagic(Square),
+    InvalidShift(Square),
+    // The square uses another hashing scheme than the rest of the table.
+    MixedHashing(Square),
+    InvalidOffset(Square),
+    // Entries given for other than one per square.
+    EntryCount(usize),
+    InvalidAttack(usize),
+    TableFill(Square),
+    Json(serde_json::Error),
+}
+
+impl From<serde_json::Error> for LoadError {
+    fn from(error: serde_json::Error) -> Self {
+        Self::Json(error)
+    }
+}
+
+// All segments of one piece concatenated into a single attack array,
+// with one entry per square pointing at the start of its segment.
+// Tables included from `magic-gen --format rust` are borrowed as is.
+pub struct MagicTable {
+    hashing: Hashing,
+    entries: Cow<'static, [MagicEntry]>,
+    attacks: Cow<'static, [BitBoard]>,
+}
+
+impl MagicTable {
+    // Rebuilds the masks from `piece` and refills the attack array using the
+    // magics found by `magic-gen`. Every square the piece can stand on needs
+    // exactly one magic; the other squares always yield EMPTY.
+    pub fn new(piece: &dyn ChessMove, magics: &[MagicEntryGen]) -> Result<Self, LoadError> {
+        Self::new_interleaved(&[piece], magics)
+    }
+
+    // Like `new`, for a table of several pieces sharing one magic per square,
+    // as written by `try_make_shared_table`.
+    pub fn new_interleaved(
+        pieces: &[&dyn ChessMove],
+        magics: &[MagicEntryGen],
+    ) -> Result<Self, LoadError> {
+        let hashing = table_hashing(magics)?;
+        check_squares(pieces[0], magics)?;
+        let mut attacks = Vec::new();
+        let mut entries = Vec::with_capacity(magics.len());
+        for gen in magics {
+            let mut entry = make_entry(pieces, gen)?;
+            entry.offset = attacks.len();
+            let table = try_make_shared_table(pieces, gen.square, &entry, hashing)
+                .map_err(|_| LoadError::TableFill(gen.square))?;
+            attacks.extend(table);
+            entries.push((gen.square, entry));
+        }
+        Ok(Self::with_entries(hashing, entries, attacks, pieces.len()))
+    }
+
+    // Uses a ready-made attack table printed by `magic-gen --attacks`,
+    // so only the masks need to be rebuilt from `piece`.
+    pub fn from_attacks(
+        piece: &dyn ChessMove,
+        magics: &[MagicEntryGen],
+        attacks: Vec<BitBoard>,
+    ) -> Result<Self, LoadError> {
+        Self::from_interleaved_attacks(&[piece], magics, attacks)
+    }
+
+    // Like `from_attacks`, for an attack table interleaving `pieces`.
+    pub fn from_interleaved_attacks(
+        pieces: &[&dyn ChessMove],
+        magics: &[MagicEntryGen],
+        attacks: Vec<BitBoard>,
+    ) -> Result<Self, LoadError> {
+        let hashing = table_hashing(magics)?;
+        check_squares(pieces[0], magics)?;
+        let mut entries = Vec::with_capacity(magics.len());
+        for gen in magics {
+            let mut entry = make_entry(pieces, gen)?;
+            entry.offset = gen.offset;
+            let end = gen
+                .size
+                .checked_mul(pieces.len())
+                .and_then(|len| gen.offset.checked_add(len));
+            if end.is_none_or(|end| end > attacks.len()) {
+                return Err(LoadError::InvalidOffset(gen.square));
+            }
+            entries.push((gen.square, entry));
+        }
+        Ok(Self::with_entries(hashing, entries, attacks, pieces.len()))
+    }
+
+    // Refills the attack array for entries generated by `magic-gen --format rust`
+    // when the static attack arrays were left out.
+    pub fn from_entries(
+        piece: &dyn ChessMove,
+        hashing: Hashing,
+        entries: &[MagicEntry],
+    ) -> Result<Self, LoadError> {
+        if entries.len() != Square::NUM {
+            return Err(LoadError::EntryCount(entries.len()));
+        }
+        let mut len = 0;
+        for (square, entry) in Square::ALL.into_iter().zip(entries) {
+            let index_bits = hashing
+                .index_bits(entry.shift)
+                .filter(|&index_bits| index_bits <= 32)
+                .ok_or(LoadError::InvalidShift(square))?;
+            if !hashing.valid_magic(entry.mask, entry.magic) {
+                return Err(LoadError::InvalidMagic(square));
+            }
+            let end = entry
+                .offset
+                .checked_add(1 << index_bits)
+                .ok_or(LoadError::InvalidOffset(square))?;
+            len = len.max(end);
+        }
+        let mut attacks = vec![BitBoard::EMPTY; len];
+        for square in piece.possible_squares() {
+            let entry = &entries[square as usize];
+            let table = try_make_table(piece, square, entry, hashing)
+                .map_err(|_| LoadError::TableFill(square))?;
+            attacks[entry.offset..entry.offset + table.len()].copy_from_slice(&table);
+        }
+        Ok(Self::from_parts(hashing, entries.to_vec(), attacks))
+    }
+
+    // Searches a magic for every square of `piece`, with as many index bits
+    // as relevant blockers.
+    pub fn search(piece: &dyn ChessMove, rng: &mut Rng) -> Self {
+        Self::search_with(piece, Hashing::Wide, rng)
+    }
+
+    // Like `search`, with `hashing`, which must support every mask of `piece`.
+    pub fn search_with(piece: &dyn ChessMove, hashing: Hashing, rng: &mut Rng) -> Self {
+        let found = piece
+            .possible_squares()
+            .into_iter()
+            .map(|square| {
+                let index_bits = piece.relevant_blockers(square).popcnt() as u8;
+                find_magic(piece, square, index_bits, hashing, rng)
+            })
+            .collect();
+        let (magics, attacks) = concat_tables(found);
+        Self::from_attacks(piece, &magics, attacks).unwrap()
+    }
+
+    // For pieces whose moves never depend on blockers: each square gets a
+    // one-slot segment, so the table is indexed by square and needs no magic.
+    pub fn steps(piece: &dyn ChessMove) -> Self {
+        let mut attacks = Vec::new();
+        let mut entries = Vec::new();
+        for square in piece.possible_squares() {
+            let entry = MagicEntry {
+                mask: BitBoard::EMPTY,
+                magic: 0,
+                shift: Hashing::Wide.shift(0),
+                offset: attacks.len(),
+            };
+            attacks.push(piece.moves(square, BitBoard::EMPTY));
+            entries.push((square, entry));
+        }
+        Self::with_entries(Hashing::Wide, entries, attacks, 1)
+    }
+
+    pub const fn from_static(
+        hashing: Hashing,
+        entries: &'static [MagicEntry; Square::NUM],
+        attacks: &'static [BitBoard],
+    ) -> Self {
+        MagicTable {
+            hashing,
+            entries: Cow::Borrowed(entries),
+            attacks: Cow::Borrowed(attacks),
+        }
+    }
+
+    pub(crate) fn from_parts(
+        hashing: Hashing,
+        entries: Vec<MagicEntry>,
+        attacks: Vec<BitBoard>,
+    ) -> Self {
+        MagicTable {
+            hashing,
+            entries: Cow::Owned(entries),
+            attacks: Cow::Owned(attacks),
+        }
+    }
+
+    // Squares missing from `entries` share one trailing EMPTY slot
+    // of `stride` attacks.
+    fn with_entries(
+        hashing: Hashing,
+        entries: Vec<(Square, MagicEntry)>,
+        mut attacks: Vec<BitBoard>,
+        stride: usize,
+    ) -> Self {
+        let empty = MagicEntry {
+            mask: BitBoard::EMPTY,
+            magic: 0,
+            shift: hashing.shift(0),
+            offset: attacks.len(),
+        };
+        if entries.len() < Square::NUM {
+            attacks.resize(attacks.len() + stride, BitBoard::EMPTY);
+        }
+        let mut by_square = vec![empty; Square::NUM];
+        for (square, entry) in entries {
+            by_square[square as usize] = entry;
+        }
+        Self::from_parts(hashing, by_square, attacks)
+    }
+
+    pub fn get(&self, square: Square, blockers: BitBoard) -> BitBoard {
+        let entry = &self.entries[square as usize];
+        self.attacks[entry.offset + self.hashing.index(entry, blockers)]
+    }
+
+    pub fn hashing(&self) -> Hashing {
+        self.hashing
+    }
+
+    pub fn entries(&self) -> &[MagicEntry] {
+        &self.entries
+    }
+
+    pub fn attacks(&self) -> &[BitBoard] {
+        &self.attacks
+    }
+}
+
+// Rook and cannon attacks under one magic per square, indexed by the cannon's
+// relevant blockers, which contain the rook's. Slot `i` of a segment keeps the
+// rook moves at `2 * i` and the cannon moves at `2 * i + 1`, so one index
+// computation and one cache line serve both pieces.
+pub struct RookCannonTable(MagicTable);
+
+impl RookCannonTable {
+    pub fn new(magics: &[MagicEntryGen]) -> Result<Self, LoadError> {
+        MagicTable::new_interleaved(&[&rook(), &cannon()], magics).map(Self)
+    }
+
+    pub fn from_attacks(
+        magics: &[MagicEntryGen],
+        attacks: Vec<BitBoard>,
+    ) -> Result<Self, LoadError> {
+        MagicTable::from_interleaved_attacks(&[&rook(), &cannon()], magics, attacks).map(Self)
+    }
+
+    // Wraps a table whose attack array is already interleaved, e.g. one read
+    // from a binary file.
+    pub(crate) fn from_table(table: MagicTable) -> Self {
+        Self(table)
+    }
+
+    // The rook moves and the cannon attacks of `square`.
+    pub fn get(&self, square: Square, blockers: BitBoard) -> (BitBoard, BitBoard) {
+        let table = &self.0;
+        let entry = &table.entries[square as usize];
+        let index = entry.offset + 2 * table.hashing.index(entry, blockers);
+        (table.attacks[index], table.attacks[index + 1])
+    }
+
+    pub fn table(&self) -> &MagicTable {
+        &self.0
+    }
+}
+
+// Attack backend of a piece moving along ranks and files.
+pub enum SliderTable {
+    Magic(MagicTable),
+    Kindergarten(KindergartenTable),
+}
+
+impl SliderTable {
+    pub fn get(&self, square: Square, blockers: BitBoard) -> BitBoard {
+        match self {
+            Self::Magic(table) => table.get(square, blockers),
+            Self::Kindergarten(table) => table.get(square, blockers),
+        }
+    }
+}
+
+impl From<MagicTable> for SliderTable {
+    fn from(table: MagicTable) -> Self {
+        Self::Magic(table)
+    }
+}
+
+impl From<KindergartenTable> for SliderTable {
+    fn from(table: KindergartenTable) -> Self {
+        Self::Kindergarten(table)
+    }
+}
+
+// Runtime move lookup built from the tables printed by `magic-gen`.
+pub struct Lookup {
+    rook: SliderTable,
+    cannon: SliderTable,
+    cannon_quiet: SliderTable,
+    rook_xray: SliderTable,
+    cannon_xray: SliderTable,
+    knight: MagicTable,
+    bishop: MagicTable,
+    king: MagicTable,
+    advisor: MagicTable,
+    red_pawn: MagicTable,
+    black_pawn: MagicTable,
+    king_step: MagicTable,
+    knight_attackers: MagicTable,
+    rook_cannon: Option<RookCannonTable>,
+}
+
+impl Lookup {
+    // The advisor, pawn and king step tables are rebuilt when missing, since they do not
+    // depend on any magic, and missing cannon quiet and x-ray tables replaced with
+    // kindergarten tables.
+    // The shared rook and cannon table is optional.
+    pub fn new(tables: &TablesGen) -> Result<Self, LoadError> {
+        let load_steps = |name, piece: &dyn ChessMove| match load_table(tables, name, piece) {
+            Err(LoadError::MissingTable(_)) => Ok(MagicTable::steps(piece)),
+            table => table,
+        };
+        let load_slider = |name, piece: &dyn ChessMove| -> Result<SliderTable, LoadError> {
+            match load_table(tables, name, piece) {
+                Err(LoadError::MissingTable(_)) => Ok(KindergartenTable::new(piece).into()),
+                table => Ok(table?.into()),
+            }
+        };
+        Ok(Lookup {
+            rook: load_table(tables, "rook", &rook())?.into(),
+            cannon: load_table(tables, "cannon", &cannon())?.into(),
+            cannon_quiet: load_slider("cannon_quiet", &cannon_quiet())?,
+            rook_xray: load_slider("rook_xray", &rook_xray())?,
+            cannon_xray: load_slider("cannon_xray", &cannon_xray())?,
+            knight: load_table(tables, "knight", &knight())?,
+            bishop: load_table(tables, "bishop", &bishop())?,
+            king: load_table(tables, "king", &king())?,
+            advisor: load_steps("advisor", &advisor())?,
+            red_pawn: load_steps("red_pawn", &red_pawn())?,
+            black_pawn: load_steps("black_pawn", &black_pawn())?,
+            king_step: load_steps("king_step", &king_step())?,
+            knight_attackers: load_table(tables, "knight_attackers", &knight_attackers())?,
+            rook_cannon: match load_interleaved(tables, "rook_cannon", &[&rook(), &cannon()]) {
+                Err(LoadError::MissingTable(_)) => None,
+                table => Some(RookCannonTable::from_table(table?)),
+            },
+        })
+    }
+
+    pub fn from_tables(
+        rook: MagicTable,
+        cannon: MagicTable,
+        knight: MagicTable,
+        bishop: MagicTable,
+        king: MagicTable,
+        knight_attackers: MagicTable,
+    ) -> Self {
+        Lookup {
+            rook: rook.into(),
+            cannon: cannon.into(),
+            cannon_quiet: KindergartenTable::new(&cannon_quiet()).into(),
+            rook_xray: KindergartenTable::new(&rook_xray()).into(),
+            cannon_xray: KindergartenTable::new(&cannon_xray()).into(),
+            knight,
+            bishop,
+            king,
+            advisor: MagicTable::steps(&advisor()),
+            red_pawn: MagicTable::steps(&red_pawn()),
+            black_pawn: MagicTable::steps(&black_pawn()),
+            king_step: MagicTable::steps(&king_step()),
+            knight_attackers,
+            rook_cannon: None,
+        }
+    }
+
+    // Searches the knight, bishop and king magics with `rng`, which takes a
+    // moment, and uses kindergarten tables for the rook and cannon, whose
+    // magic search takes minutes. Handy when no generated tables are at hand;
+    // there is no shared rook and cannon table either.
+    pub fn search(rng: &mut Rng) -> Self {
+        Lookup {
+            rook: KindergartenTable::new(&rook()).into(),
+            cannon: KindergartenTable::new(&cannon()).into(),
+            cannon_quiet: KindergartenTable::new(&cannon_quiet()).into(),
+            rook_xray: KindergartenTable::new(&rook_xray()).into(),
+            cannon_xray: KindergartenTable::new(&cannon_xray()).into(),
+            knight: MagicTable::search(&knight(), rng),
+            bishop: MagicTable::search(&bishop(), rng),
+            king: MagicTable::search(&king(), rng),
+            knight_attackers: MagicTable::search(&knight_attackers(), rng),
+            advisor: MagicTable::steps(&advisor()),
+            red_pawn: MagicTable::steps(&red_pawn()),
+            black_pawn: MagicTable::steps(&black_pawn()),
+            king_step: MagicTable::steps(&king_step()),
+            rook_cannon: None,
+        }
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, LoadError> {
+        let tables: TablesGen = serde_json::from_str(json)?;
+        Self::new(&tables)
+    }
+
+    // Loads a file written by `magic-gen --format binary`.
+    pub fn from_binary(data: &[u8]) -> Result<Self, BinaryError> {
+        let tables = read_tables(data)?;
+        let find = |kind| {
+            tables
+                .iter()
+                .find(|table| table.kind() == kind)
+                .map(BinaryTable::to_magic_table)
+        };
+        let load = |kind| find(kind).ok_or(BinaryError::MissingKind(kind));
+        let load_steps =
+            |kind, piece: &dyn ChessMove| find(kind).unwrap_or_else(|| MagicTable::steps(piece));
+        let load_slider = |kind, piece: &dyn ChessMove| -> SliderTable {
+            match find(kind) {
+                Some(table) => table.into(),
+                None => KindergartenTable::new(piece).into(),
+            }
+        };
+        Ok(Lookup {
+            rook: load(TableKind::Rook)?.into(),
+            cannon: load(TableKind::Cannon)?.into(),
+            cannon_quiet: load_slider(TableKind::CannonQuiet, &cannon_quiet()),
+            rook_xray: load_slider(TableKind::RookXray, &rook_xray()),
+            cannon_xray: load_slider(TableKind::CannonXray, &cannon_xray()),
+            knight: load(TableKind::Knight)?,
+            bishop: load(TableKind::Bishop)?,
+            king: load(TableKind::King)?,
+            advisor: load_steps(TableKind::Advisor, &advisor()),
+            red_pawn: load_steps(TableKind::RedPawn, &red_pawn()),
+            black_pawn: load_steps(TableKind::BlackPawn, &black_pawn()),
+            king_step: load_steps(TableKind::KingStep, &king_step()),
+            knight_attackers: load(TableKind::KnightAttackers)?,
+            rook_cannon: find(TableKind::RookCannon).map(RookCannonTable::from_table),
+        })
+    }
+
+    // Switches the rook to kindergarten tables, dropping its magic tables.
+    pub fn with_kindergarten_rook(self) -> Self {
+        Lookup {
+            rook: KindergartenTable::new(&rook()).into(),
+            rook_xray: KindergartenTable::new(&rook_xray()).into(),
+            rook_cannon: None,
+            ..self
+        }
+    }
+
+    // Switches the cannon tables to kindergarten tables, dropping their magic tables.
+    pub fn with_kindergarten_cannon(self) -> Self {
+        Lookup {
+            cannon: KindergartenTable::new(&cannon()).into(),
+            cannon_quiet: KindergartenTable::new(&cannon_quiet()).into(),
+            cannon_xray: KindergartenTable::new(&cannon_xray()).into(),
+            rook_cannon: None,
+            ..self
+        }
+    }
+
+    // Uses `table` for `rook_cannon_attacks`.
+    pub fn with_rook_cannon(self, table: RookCannonTable) -> Self {
+        Lookup {
+            rook_cannon: Some(table),
+            ..self
+        }
+    }
+
+    pub fn rook_moves(&self, square: Square, occupied: BitBoard) -> BitBoard {
+        self.rook.get(square, occupied)
+    }
+
+    // Squares the cannon on `square` captures over exactly one screen.
+    pub fn cannon_captures(&self, square: Square, occupied: BitBoard) -> BitBoard {
+        self.cannon.get(square, occupied)
+    }
+
+    // Squares the cannon on `square` slides to without capturing.
+    pub fn cannon_quiets(&self, square: Square, occupied: BitBoard) -> BitBoard {
+        self.cannon_quiet.get(square, occupied)
+    }
+
+    // Squares the rook on `square` attacks through its first blocker.
+    pub fn rook_xrays(&self, square: Square, occupied: BitBoard) -> BitBoard {
+        self.rook_xray.get(square, occupied)
+    }
+
+    // Squares the cannon on `square` captures over exactly two screens.
+    pub fn cannon_xrays(&self, square: Square, occupied: BitBoard) -> BitBoard {
+        self.cannon_xray.get(square, occupied)
+    }
+
+    // The rook moves and the cannon captures from `square`, with a single index
+    // into the shared table when one was loaded.
+    pub fn rook_cannon_attacks(&self, square: Square, occupied: BitBoard) -> (BitBoard, BitBoard) {
+        match &self.rook_cannon {
+            Some(table) => table.get(square, occupied),
+            None => (
+                self.rook_moves(square, occupied),
+                self.cannon_captures(square, occupied),
+            ),
+        }
+    }
+
+    // Moves of the rook or the cannon on `square`. With the shared table loaded
+    // both come from one index computation: the cannon slides wherever the
+    // rook moves without capturing.
+    pub fn slider_moves(&self, square: Square, piece: Piece, occupied: BitBoard) -> BitBoard {
+        match (&self.rook_cannon, piece) {
+            (Some(table), Piece::Rook) => table.get(square, occupied).0,
+            (Some(table), _) => {
+                let (rook, cannon) = table.get(square, occupied);
+                rook & !occupied | cannon
+            }
+            (None, Piece::Rook) => self.rook_moves(square, occupied),
+            (None, _) => {
+                self.cannon_quiets(square, occupied) | self.cannon_captures(square, occupied)
+            }
+        }
+    }
+
+    pub fn knight_moves(&self, square: Square, occupied: BitBoard) -> BitBoard {
+        self.knight.get(square, occupied)
+    }
+
+    // Squares from which a knight attacks `square`.
+    pub fn knight_attackers(&self, square: Square, occupied: BitBoard) -> BitBoard {
+        self.knight_attackers.get(square, occupied)
+    }
+
+    // Squares on which a piece may block a knight attacking `square`.
+    pub fn knight_legs(&self, square: Square) -> BitBoard {
+        self.knight_attackers.entries()[square as usize].mask
+    }
+
+    pub fn bishop_moves(&self, square: Square, occupied: BitBoard) -> BitBoard {
+        self.bishop.get(square, occupied)
+    }
+
+    pub fn king_forbidden(&self, square: Square, occupied: BitBoard) -> BitBoard {
+        self.king.get(square, occupied)
+    }
+
+    pub fn king_steps(&self, square: Square) -> BitBoard {
+        self.king_step.get(square, BitBoard::EMPTY)
+    }
+
+    // Steps of the king on `square` that do not face the other king on
+    // `their_king`, which sees through the square the king leaves.
+    pub fn king_moves(&self, square: Square, their_king: Square, occupied: BitBoard) -> BitBoard {
+        let occupied = occupied & !square.bitboard();
+        self.king_steps(square) & !self.king_forbidden(their_king, occupied)
+    }
+
+    pub fn advisor_moves(&self, square: Square) -> BitBoard {
+        self.advisor.get(square, BitBoard::EMPTY)
+    }
+
+    pub fn pawn_moves(&self, square: Square, color: Color) -> BitBoard {
+        match color {
+            Color::Red => self.red_pawn.get(square, BitBoard::EMPTY),
+            Color::Black => self.black_pawn.get(square, BitBoard::EMPTY),
+        }
+    }
+}
+
+fn make_entry(pieces: &[&dyn ChessMove], gen: &MagicEntryGen) -> Result<MagicEntry, LoadError> {
+    let magic = gen
+        .parse_magic()
+        .ok_or(LoadError::InvalidMagic(gen.square))?;
+    match gen.hashing.index_bits(gen.shift) {
+        Some(index_bits) if index_bits <= 32 && gen.size == 1 << index_bits => {}
+        _ => return Err(LoadError::InvalidShift(gen.square)),
+    }
+    let mask = shared_mask(pieces, gen.square);
+    if !gen.hashing.valid_magic(mask, magic) {
+        return Err(LoadError::InvalidMagic(gen.square));
+    }
+    Ok(MagicEntry {
+        mask,
+        magic,
+        shift: gen.shift,
+        offset: 0,
+    })
+}
+
+// Every square `piece` can stand on needs exactly one magic.
+fn check_squares(piece: &dyn ChessMove, magics: &[MagicEntryGen]) -> Result<(), LoadError> {
+    let mut seen = [false; Square::NUM];
+    for gen in magics {
+        if std::mem::replace(&mut seen[gen.square as usize], true) {
+            return Err(LoadError::DuplicateSquare(gen.square));
+        }
+    }
+    match piece
+        .possible_squares()
+        .into_iter()
+        .find(|&square| !seen[square as usize])
+    {
+        Some(square) => Err(LoadError::MissingSquare(square)),
+        None => Ok(()),
+    }
+}
+
+// All squares of a table must share one hashing scheme.
+fn table_hashing(magics: &[MagicEntryGen]) -> Result<Hashing, LoadError> {
+    let hashing = magics.first().map_or(Hashing::Wide, |gen| gen.hashing);
+    match magics.iter().find(|gen| gen.hashing != hashing) {
+        Some(gen) => Err(LoadError::MixedHashing(gen.square)),
+        None => Ok(hashing),
+    }
+}
+
+// Prefers the ready-made `<name>_attack_table` and falls back to
+// rebuilding it from `<name>_magic_table`.
+fn load_table(
+    tables: &TablesGen,
+    name: &str,
+    piece: &dyn ChessMove,
+) -> Result<MagicTable, LoadError> {
+    load_interleaved(tables, name, &[piece])
+}
+
+fn load_interleaved(
+    tables: &TablesGen,
+    name: &str,
+    pieces: &[&dyn ChessMove],
+) -> Result<MagicTable, LoadError> {
+    let magic_name = format!("{}_magic_table", name);
+    let magics = match tables.get(&magic_name) {
+        Some(TableGen::Magics(magics)) => magics,
+        Some(TableGen::Attacks(_)) => return Err(LoadError::InvalidTable(magic_name)),
+        None => return Err(LoadError::MissingTable(magic_name)),
+    };
+    let attack_name = format!("{}_attack_table", name);
+    match tables.get(&attack_name) {
+        Some(TableGen::Attacks(attacks)) => {
+            let attacks = attacks
+                .iter()
+                .enumerate()
+                .map(|(i, attack)| {
+                    parse_hex(attack)
+                        .map(BitBoard)
+                        .ok_or(LoadError::InvalidAttack(i))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            MagicTable::from_interleaved_attacks(pieces, magics, attacks)
+        }
+        Some(TableGen::Magics(_)) => Err(LoadError::InvalidTable(attack_name)),
+        None => MagicTable::new_interleaved(pieces, magics),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generate::{find_shared_magic, format_hex},
+        rook::{Slider, SLIDER_ONE_STEP},
+    };
+
+    // The magics and attacks of a table found by `MagicTable::search_with`,
+    // as `magic-gen` would print them.
+    fn search_magics(
+        piece: &dyn ChessMove,
+        hashing: Hashing,
+    ) -> (Vec<MagicEntryGen>, Vec<BitBoard>) {
+        let table = MagicTable::search_with(piece, hashing, &mut Rng::default());
+        let magics = piece
+            .possible_squares()
+            .into_iter()
+            .map(|square| {
+                let entry = &table.entries()[square as usize];
+                MagicEntryGen {
+                    square,
+                    magic: format_hex(entry.magic),
+                    shift: entry.shift,
+                    size: 1 << hashing.index_bits(entry.shift).unwrap(),
+                    offset: entry.offset,
+                    hashing,
+                }
+            })
+            .collect();
+        (magics, table.attacks().to_vec())
+    }
+
+    #[test]
+    fn test_magic_table_knight() {
+        let knight = knight();
+        let (magics, _) = search_magics(&knight, Hashing::Wide);
+        let table = MagicTable::new(&knight, &magics).unwrap();
+        let blockers = Square::E5.bitboard() | Square::D3.bitboard() | Square::B2.bitboard();
+        for square in Square::ALL {
+            assert!(
+                table.get(square, blockers) == knight.moves(square, blockers),
+                "fail with square = {:?}!",
+                square
+            );
+        }
+    }
+
+    #[test]
+    fn test_magic_table_missing_square() {
+        let bishop = bishop();
+        let (magics, _) = search_magics(&bishop, Hashing::Wide);
+        let table = MagicTable::new(&bishop, &magics).unwrap();
+        assert!(table.get(Square::E5, BitBoard::EMPTY).is_empty());
+        assert!(
+            table.get(Square::C1, BitBoard::EMPTY) == bishop.moves(Square::C1, BitBoard::EMPTY)
+        );
+    }
+
+    #[test]
+    fn test_magic_table_incomplete() {
+        let knight = knight();
+        let (mut magics, attacks) = search_magics(&knight, Hashing::Wide);
+        let last = magics.pop().unwrap();
+        assert!(matches!(
+            MagicTable::new(&knight, &magics),
+            Err(LoadError::MissingSquare(square)) if square == last.square
+        ));
+        magics.push(magics[0].clone());
+        assert!(matches!(
+            MagicTable::from_attacks(&knight, &magics, attacks),
+            Err(LoadError::DuplicateSquare(square)) if square == magics[0].square
+        ));
+    }
+
+    #[test]
+    fn test_magic_table_invalid_fold() {
+        let knight = knight();
+        let (mut magics, _) = search_magics(&knight, Hashing::Folded);
+        let magic = magics[0].parse_magic().unwrap() as u64 as u128;
+        magics[0].magic = format_hex(200 << 64 | magic);
+        assert!(matches!(
+            MagicTable::new(&knight, &magics),
+            Err(LoadError::InvalidMagic(square)) if square == magics[0].square
+        ));
+    }
+
+    #[test]
+    fn test_magic_table_from_attacks() {
+        let bishop = bishop();
+        let (magics, attacks) = search_magics(&bishop, Hashing::Wide);
+        let mut hostile = magics.clone();
+        hostile[0].offset = usize::MAX;
+        assert!(matches!(
+            MagicTable::from_attacks(&bishop, &hostile, attacks.clone()),
+            Err(LoadError::InvalidOffset(square)) if square == hostile[0].square
+        ));
+        let table = MagicTable::from_attacks(&bishop, &magics, attacks).unwrap();
+        let blockers = Square::D2.bitboard() | Square::F4.bitboard();
+        for square in bishop.possible_squares() {
+            assert!(
+                table.get(square, blockers) == bishop.moves(square, blockers),
+                "fail with square = {:?}!",
+                square
+            );
+        }
+    }
+
+    #[test]
+    fn test_magic_table_from_entries() {
+        let knight = knight();
+        let (magics, _) = search_magics(&knight, Hashing::Wide);
+        let table = MagicTable::new(&knight, &magics).unwrap();
+        let rebuilt = MagicTable::from_entries(&knight, Hashing::Wide, table.entries()).unwrap();
+        assert!(rebuilt.attacks() == table.attacks());
+        assert!(matches!(
+            MagicTable::from_entries(&knight, Hashing::Wide, &table.entries()[1..]),
+            Err(LoadError::EntryCount(89))
+        ));
+        let mut entries = table.entries().to_vec();
+        entries[0].shift = 0;
+        assert!(matches!(
+            MagicTable::from_entries(&knight, Hashing::Wide, &entries),
+            Err(LoadError::InvalidShift(Square::A1))
+        ));
+        entries[0] = table.entries()[0];
+        entries[0].offset = usize::MAX;
+        assert!(matches!(
+            MagicTable::from_entries(&knight, Hashing::Wide, &entries),
+            Err(LoadError::InvalidOffset(Square::A1))
+        ));
+    }
+
+    #[test]
+    fn test_magic_table_folded() {
+        for piece in [&knight() as &dyn ChessMove, &king()] {
+            let (magics, _) = search_magics(piece, Hashing::Folded);
+            let table = MagicTable::new(piece, &magics).unwrap();
+            assert!(table.hashing() == Hashing::Folded);
+            let mut rng = Rng::default();
+            for _ in 0..100 {
+                let blockers = BitBoard(rng.next_u128() & rng.next_u128() & ((1 << 90) - 1));
+                for square in piece.possible_squares() {
+                    assert!(
+                        table.get(square, blockers) == piece.moves(square, blockers),
+                        "fail with square = {:?}, blockers = {:?}!",
+                        square,
+                        blockers
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_magic_table_mixed_hashing() {
+        let knight = knight();
+        let (mut magics, _) = search_magics(&knight, Hashing::Wide);
+        magics[1].hashing = Hashing::Folded;
+        assert!(matches!(
+            MagicTable::new(&knight, &magics),
+            Err(LoadError::MixedHashing(_))
+        ));
+    }
+
+    #[test]
+    fn test_magic_table_steps() {
+        for piece in [
+            &advisor() as &dyn ChessMove,
+            &red_pawn(),
+            &black_pawn(),
+            &king_step(),
+        ] {
+            let table = MagicTable::steps(piece);
+            let blockers = Square::E2.bitboard() | Square::E6.bitboard();
+            for square in piece.possible_squares() {
+                assert!(table.get(square, blockers) == piece.moves(square, blockers));
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup_king_moves() {
+        let lookup = Lookup::search(&mut Rng::default());
+        let occupied = Square::E2.bitboard() | Square::E0.bitboard();
+        assert!(lookup.king_steps(Square::E2).popcnt() == 4);
+        assert!(lookup.king_steps(Square::D0) == Square::E0.bitboard() | Square::D9.bitboard());
+        assert!(
+            lookup.king_moves(Square::E2, Square::E0, occupied)
+                == Square::D2.bitboard() | Square::F2.bitboard()
+        );
+        let occupied = occupied | Square::E5.bitboard();
+        assert!(lookup.king_moves(Square::E2, Square::E0, occupied).popcnt() == 4);
+    }
+
+    #[test]
+    fn test_lookup_cannon() {
+        let lookup = Lookup::search(&mut Rng::default());
+        let (cannon, cannon_quiet) = (cannon(), cannon_quiet());
+        let mut rng = Rng::default();
+        for _ in 0..100 {
+            let occupied = BitBoard(rng.next_u128() & rng.next_u128() & BitBoard::FULL.0);
+            for square in Square::ALL {
+                let occupied = occupied & !square.bitboard();
+                let (mut quiets, mut captures) = (BitBoard::EMPTY, BitBoard::EMPTY);
+                for (df, dr) in [(1, 0), (0, 1), (-1, 0), (0, -1)] {
+                    let mut ray = std::iter::successors(square.try_offset(df, dr), |ray| {
+                        ray.try_offset(df, dr)
+                    });
+                    for empty in ray.by_ref().take_while(|&ray| !occupied.has(ray)) {
+                        quiets |= empty.bitboard();
+                    }
+                    if let Some(target) = ray.find(|&ray| occupied.has(ray)) {
+                        captures |= target.bitboard();
+                    }
+                }
+                // The pieces themselves are exact, whatever lies on the edge.
+                assert!(
+                    cannon_quiet.moves(square, occupied) == quiets
+                        && cannon.moves(square, occupied) == captures
+                        && lookup.cannon_quiets(square, occupied) == quiets
+                        && lookup.cannon_captures(square, occupied) == captures,
+                    "fail with square = {:?}, occupied = {:?}!",
+                    square,
+                    occupied
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_rook_cannon_table() {
+        // A rook limited to two squares, so only those need a magic. Next to a
+        // corner the cannon has the fewest relevant blockers, and spare index
+        // bits keep the search short.
+        let squares = [Square::B2, Square::H9];
+        let rook = Slider::new(SLIDER_ONE_STEP, squares.to_vec());
+        let cannon = cannon();
+        let mut rng = Rng::default();
+        let found = squares
+            .into_iter()
+            .map(|square| {
+                let index_bits = shared_mask(&[&rook, &cannon], square).popcnt() as u8 + 3;
+                find_shared_magic(
+                    &[&rook, &cannon],
+                    square,
+                    index_bits,
+                    Hashing::Wide,
+                    &mut rng,
+                )
+            })
+            .collect();
+        let (magics, attacks) = concat_tables(found);
+        let table = MagicTable::from_interleaved_attacks(&[&rook, &cannon], &magics, attacks);
+        let lookup = Lookup::search(&mut Rng::default())
+            .with_rook_cannon(RookCannonTable::from_table(table.unwrap()));
+        for _ in 0..100 {
+            let occupied = BitBoard(rng.next_u128() & rng.next_u128() & BitBoard::FULL.0);
+            for square in Square::ALL {
+                let occupied = occupied & !square.bitboard();
+                let expected = if squares.contains(&square) {
+                    (
+                        lookup.rook_moves(square, occupied),
+                        lookup.cannon_captures(square, occupied),
+                    )
+                } else {
+                    (BitBoard::EMPTY, BitBoard::EMPTY)
+                };
+                assert!(
+                    lookup.rook_cannon_attacks(square, occupied) == expected,
+                    "fail with square = {:?}, occupied = {:?}!",
+                    square,
+                    occupied
+                );
+                if squares.contains(&square) {
+                    let cannon = lookup.cannon_quiets(square, occupied) | expected.1;
+                    assert!(lookup.slider_moves(square, Piece::Rook, occupied) == expected.0);
+                    assert!(lookup.slider_moves(square, Piece::Cannon, occupied) == cannon);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_lookup_missing_table() {
+        let tables = TablesGen::new();
+        assert!(matches!(
+            Lookup::new(&tables),
+            Err(LoadError::MissingTable(_))
+        ));
+    }
+}
@@ -0,0 +1,316 @@
+use std::sync::OnceLock;
+
+use types::{between, BitBoard, Color, Move, Piece, Position, Square};
+
+use crate::{lookup::Lookup, rng::Rng};
+
+pub type MoveList = Vec<Move>;
+
+static LOOKUP: OnceLock<Lookup> = OnceLock::new();
+
+// Installs the tables used by `generate_legal_moves`, e.g. ones loaded from a
+// file written by `magic-gen`. Fails with `lookup` if tables are already in use.
+pub fn set_lookup(lookup: Lookup) -> Result<(), Box<Lookup>> {
+    LOOKUP.set(lookup).map_err(Box::new)
+}
+
+// The installed tables, or tables found by `Lookup::search` on first use.
+pub fn lookup() -> &'static Lookup {
+    LOOKUP.get_or_init(|| Lookup::search(&mut Rng::default()))
+}
+
+pub fn generate_legal_moves(position: &Position) -> MoveList {
+    lookup().legal_moves(position)
+}
+
+pub fn pinned_pieces(position: &Position, color: Color) -> BitBoard {
+    lookup().pinned_pieces(position, color)
+}
+
+pub fn attackers_to(position: &Position, square: Square, occupied: BitBoard) -> BitBoard {
+    lookup().attackers_to(position, square, occupied)
+}
+
+impl Lookup {
+    // Squares the piece of `color` on `square` may move to, ignoring checks.
+    fn piece_moves(
+        &self,
+        position: &Position,
+        square: Square,
+        piece: Piece,
+        color: Color,
+    ) -> BitBoard {
+        let occupied = position.occupied();
+        let moves = match piece {
+            Piece::King => match position.king(!color) {
+                Some(their_king) => self.king_moves(square, their_king, occupied),
+                None => self.king_steps(square),
+            },
+            Piece::Advisor => self.advisor_moves(square),
+            Piece::Bishop => self.bishop_moves(square, occupied),
+            Piece::Knight => self.knight_moves(square, occupied),
+            Piece::Rook | Piece::Cannon => self.slider_moves(square, piece, occupied),
+            Piece::Pawn => self.pawn_moves(square, color),
+        };
+        moves & !position.colors(color)
+    }
+
+    // Pieces of both colors attacking `square`, with `occupied` standing in
+    // for the occupancy of the position, so pieces missing from it neither
+    // block nor attack, as when exchanges are played out. A king attacks the
+    // other king when they face each other.
+    pub fn attackers_to(
+        &self,
+        position: &Position,
+        square: Square,
+        occupied: BitBoard,
+    ) -> BitBoard {
+        let pieces = |piece| position.pieces(piece);
+        let (rooks, cannons) = self.rook_cannon_attacks(square, occupied);
+        let mut attackers = rooks & pieces(Piece::Rook)
+            | cannons & pieces(Piece::Cannon)
+            | self.knight_attackers(square, occupied) & pieces(Piece::Knight)
+            | self.bishop_moves(square, occupied) & pieces(Piece::Bishop)
+            | self.advisor_moves(square) & pieces(Piece::Advisor)
+            | self.king_steps(square) & pieces(Piece::King);
+        if pieces(Piece::King).has(square) {
+            attackers |= self.king_forbidden(square, occupied) & pieces(Piece::King);
+        }
+        // Pawns do not attack back the way they came, so try the pawns next
+        // to the square, found as the rook moves on a full board.
+        let neighbours = self.rook_moves(square, BitBoard::FULL);
+        for color in Color::ALL {
+            for pawn in neighbours & position.colored_pieces(color, Piece::Pawn) {
+                if self.pawn_moves(pawn, color).has(square) {
+                    attackers |= pawn.bitboard();
+                }
+            }
+        }
+        attackers & occupied
+    }
+
+    // Whether the king of `color` is attacked, or faces the other king.
+    fn in_check(&self, position: &Position, color: Color) -> bool {
+        let Some(king) = position.king(color) else {
+            return false;
+        };
+        let attackers = self.attackers_to(position, king, position.occupied());
+        !(attackers & position.colors(!color)).is_empty()
+    }
+
+    // Pieces of `color` whose leaving their square would expose its king: the
+    // only piece between the king and a rook or the other king, either of the
+    // two pieces between the king and a cannon, and a piece on the leg of a
+    // knight that would otherwise attack the king.
+    pub fn pinned_pieces(&self, position: &Position, color: Color) -> BitBoard {
+        let Some(king) = position.king(color) else {
+            return BitBoard::EMPTY;
+        };
+        let occupied = position.occupied();
+        let theirs = |piece| position.colored_pieces(!color, piece);
+        let mut pinned = BitBoard::EMPTY;
+        let pinners = self.rook_xrays(king, occupied) & (theirs(Piece::Rook) | theirs(Piece::King));
+        let cannons = self.cannon_xrays(king, occupied) & theirs(Piece::Cannon);
+        for pinner in pinners | cannons {
+            pinned |= between(king, pinner);
+        }
+        let knights = self.knight_attackers(king, occupied) & theirs(Piece::Knight);
+        for leg in self.knight_legs(king) & position.colors(color) {
+            let unblocked = self.knight_attackers(king, occupied & !leg.bitboard());
+            if !(unblocked & theirs(Piece::Knight) & !knights).is_empty() {
+                pinned |= leg.bitboard();
+            }
+        }
+        pinned & position.colors(color)
+    }
+
+    // Empty squares between the king of `color` and a cannon facing it,
+    // where any piece would become a screen.
+    fn cannon_screens(&self, position: &Position, color: Color) -> BitBoard {
+        let Some(king) = position.king(color) else {
+            return BitBoard::EMPTY;
+        };
+        let cannons = self.rook_moves(king, position.occupied());
+        let mut screens = BitBoard::EMPTY;
+        for cannon in cannons & position.colored_pieces(!color, Piece::Cannon) {
+            screens |= between(king, cannon);
+        }
+        screens
+    }
+
+    // Moves of the side to move that leave its king neither attacked
+    // nor facing the other king. Out of check, a move of a piece other than
+    // the king that is not pinned and does not become a cannon screen is
+    // legal as is; only the rest are played out and tested.
+    pub fn legal_moves(&self, position: &Position) -> MoveList {
+        let color = position.side_to_move();
+        let in_check = self.in_check(position, color);
+        let (pinned, screens) = if in_check {
+            (BitBoard::EMPTY, BitBoard::EMPTY)
+        } else {
+            (
+                self.pinned_pieces(position, color),
+                self.cannon_screens(position, color),
+            )
+        };
+        let mut moves = MoveList::new();
+        for from in position.colors(color) {
+            let piece = position.piece_on(from).unwrap();
+            let free = !in_check && piece != Piece::King && !pinned.has(from);
+            for to in self.piece_moves(position, from, piece, color) {
+                let mv = Move {
+                    from,
+                    to,
+                    captured: position.piece_on(to),
+                };
+                if free && !screens.has(to) {
+                    moves.push(mv);
+                    continue;
+                }
+                let mut child = position.clone();
+                child.play_unchecked(mv);
+                if !self.in_check(&child, color) {
+                    moves.push(mv);
+                }
+            }
+        }
+        moves
+    }
+
+    pub fn is_check(&self, position: &Position) -> bool {
+        self.in_check(position, position.side_to_move())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn legal(fen: &str) -> MoveList {
+        generate_legal_moves(&Position::from_fen(fen).unwrap())
+    }
+
+    fn has(moves: &MoveList, from: Square, to: Square) -> bool {
+        moves.iter().any(|mv| mv.from == from && mv.to == to)
+    }
+
+    #[test]
+    fn test_start_position() {
+        let moves = legal(Position::START_FEN);
+        assert!(moves.len() == 44, "fail with {} moves!", moves.len());
+        assert!(has(&moves, Square::H3, Square::E3));
+        assert!(has(&moves, Square::B3, Square::B0));
+        assert!(!has(&moves, Square::B3, Square::B8));
+    }
+
+    #[test]
+    fn test_flying_general() {
+        // The red rook is the only piece between the kings.
+        let moves = legal("4k4/9/9/9/9/9/9/9/4R4/4K4 w - - 0 1");
+        assert!(moves
+            .iter()
+            .all(|mv| mv.from != Square::E2 || mv.to.file() == Square::E2.file()));
+        assert!(has(&moves, Square::E2, Square::E5));
+        // The king may not step onto the open file of the other king.
+        let moves = legal("3k5/9/9/9/9/9/9/9/9/4K4 w - - 0 1");
+        assert!(!has(&moves, Square::E1, Square::D1));
+        assert!(has(&moves, Square::E1, Square::E2));
+    }
+
+    #[test]
+    fn test_lame_leg_and_eye() {
+        // B2 is a knight leg and a bishop eye, C1 a knight leg.
+        let moves = legal("3k5/9/9/9/9/9/9/9/1P7/1NB1K4 w - - 0 1");
+        assert!(!has(&moves, Square::B1, Square::A3));
+        assert!(!has(&moves, Square::B1, Square::C3));
+        assert!(!has(&moves, Square::B1, Square::D2));
+        assert!(!has(&moves, Square::C1, Square::A3));
+        assert!(has(&moves, Square::C1, Square::E3));
+    }
+
+    #[test]
+    fn test_river() {
+        // Bishops stay home, pawns only move sideways across the river.
+        let moves = legal("3k5/9/9/9/2P6/2B3P2/9/9/9/4K4 w - - 0 1");
+        assert!(!has(&moves, Square::C5, Square::A7));
+        assert!(!has(&moves, Square::C5, Square::E7));
+        assert!(has(&moves, Square::C5, Square::E3));
+        assert!(has(&moves, Square::C6, Square::B6) && has(&moves, Square::C6, Square::C7));
+        assert!(!has(&moves, Square::G5, Square::F5));
+        assert!(has(&moves, Square::G5, Square::G6));
+    }
+
+    #[test]
+    fn test_captured_piece() {
+        let moves = legal("3k5/9/9/9/9/9/9/9/r8/R3K4 w - - 0 1");
+        // A capture parsed from ICCS carries no captured piece but still
+        // matches the generated move.
+        let parsed = Move::from_iccs("a0a1").unwrap();
+        let found = moves.iter().find(|&&mv| mv == parsed).unwrap();
+        assert!(found.captured == Some(Piece::Rook));
+        let quiet = moves
+            .iter()
+            .find(|&&mv| mv == Move::new(Square::E1, Square::F1));
+        assert!(quiet.unwrap().captured.is_none());
+    }
+
+    #[test]
+    fn test_cannon_screen() {
+        let moves = legal("3k5/9/9/9/1p7/9/9/1p7/9/1C2K4 w - - 0 1");
+        assert!(has(&moves, Square::B1, Square::B2));
+        assert!(!has(&moves, Square::B1, Square::B3));
+        assert!(has(&moves, Square::B1, Square::B6));
+        assert!(!has(&moves, Square::B1, Square::B7));
+    }
+
+    #[test]
+    fn test_pinned_pieces() {
+        let pinned = |fen| pinned_pieces(&Position::from_fen(fen).unwrap(), Color::Red);
+        // The knight on E2 is pinned by the rook, the rook on D2 blocks the
+        // leg of the knight on D3.
+        assert!(
+            pinned("3k5/9/9/9/9/4r4/9/3n5/3RN4/4K4 w - - 0 1")
+                == Square::E2.bitboard() | Square::D2.bitboard()
+        );
+        // Either piece between the king and the cannon would leave a screen.
+        assert!(
+            pinned("4k4/9/9/9/4c4/9/9/4N4/4B4/4K4 w - - 0 1")
+                == Square::E2.bitboard() | Square::E3.bitboard()
+        );
+        assert!(pinned("4k4/9/9/9/9/9/9/9/4N4/4K4 w - - 0 1") == Square::E2.bitboard());
+    }
+
+    #[test]
+    fn test_attackers_to() {
+        // The red knight on C5 is lame, the black pawn on D5 only moves sideways
+        // and down, and the red pawn on D6 has crossed the river.
+        let position =
+            Position::from_fen("3k5/9/9/4p1n2/3P4r/2NpP4/9/4C4/9/4K4 w - - 0 1").unwrap();
+        let occupied = position.occupied();
+        let attackers = attackers_to(&position, Square::E6, occupied);
+        let expected = [
+            Square::E5,
+            Square::E7,
+            Square::D6,
+            Square::G7,
+            Square::I6,
+            Square::E3,
+        ];
+        assert!(attackers.popcnt() == 6 && expected.iter().all(|&sq| attackers.has(sq)));
+        // Without the red pawn on E5 the cannon has no screen.
+        let attackers = attackers_to(&position, Square::E6, occupied & !Square::E5.bitboard());
+        assert!(attackers.popcnt() == 4 && !attackers.has(Square::E3));
+        let position = Position::from_fen("4k4/9/9/9/9/9/9/9/9/4K4 w - - 0 1").unwrap();
+        assert!(attackers_to(&position, Square::E0, position.occupied()) == Square::E1.bitboard());
+    }
+
+    #[test]
+    fn test_check_evasion() {
+        // The black rook checks along the first rank and the D file is open,
+        // so stepping up is the only way out.
+        let position = Position::from_fen("3k5/9/9/9/9/9/9/9/R8/4K3r w - - 0 1").unwrap();
+        assert!(lookup().is_check(&position));
+        let moves = generate_legal_moves(&position);
+        assert!(moves == [Move::new(Square::E1, Square::E2)]);
+    }
+}
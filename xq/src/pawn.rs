@@ -0,0 +1,94 @@
+use types::{BitBoard, Color, File, Rank, Square};
+
+use crate::generate::ChessMove;
+
+// Pawns step forward, and also sideways once across the river; they never
+// stand behind their starting rank.
+pub struct Pawn {
+    color: Color,
+}
+
+impl Pawn {
+    pub fn new(color: Color) -> Self {
+        Pawn { color }
+    }
+
+    fn forward(&self) -> i8 {
+        match self.color {
+            Color::Red => 1,
+            Color::Black => -1,
+        }
+    }
+
+    fn crossed_river(&self, square: Square) -> bool {
+        match self.color {
+            Color::Red => square.rank() >= Rank::Sixth,
+            Color::Black => square.rank() <= Rank::Fifth,
+        }
+    }
+}
+
+impl ChessMove for Pawn {
+    fn moves(&self, square: Square, _: BitBoard) -> BitBoard {
+        let mut moves = BitBoard::EMPTY;
+        let mut deltas = vec![(0, self.forward())];
+        if self.crossed_river(square) {
+            deltas.extend([(1, 0), (-1, 0)]);
+        }
+        for (df, dr) in deltas {
+            if let Some(dst) = square.try_offset(df, dr) {
+                moves |= dst.bitboard();
+            }
+        }
+        moves
+    }
+
+    fn relevant_blockers(&self, _: Square) -> BitBoard {
+        BitBoard::EMPTY
+    }
+
+    // Files A, C, E, G and I before the river, anywhere after it.
+    fn possible_squares(&self) -> Vec<Square> {
+        let start = match self.color {
+            Color::Red => Rank::Fourth,
+            Color::Black => Rank::Seventh,
+        };
+        Square::ALL
+            .into_iter()
+            .filter(|&square| {
+                let advanced = (square.rank() as i8 - start as i8) * self.forward() >= 0;
+                let file = matches!(
+                    square.file(),
+                    File::A | File::C | File::E | File::G | File::I
+                );
+                self.crossed_river(square) || (advanced && file)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pawn_squares() {
+        let red = Pawn::new(Color::Red).possible_squares();
+        let black = Pawn::new(Color::Black).possible_squares();
+        assert!(red.len() == 10 + 45 && black.len() == 10 + 45);
+        assert!(
+            red.contains(&Square::A4) && !red.contains(&Square::B4) && !red.contains(&Square::A3)
+        );
+        assert!(black.contains(&Square::C7) && !black.contains(&Square::C8));
+        assert!(
+            Pawn::new(Color::Black).moves(Square::E5, BitBoard::EMPTY)
+                == Square::E4.bitboard() | Square::D5.bitboard() | Square::F5.bitboard()
+        );
+        assert!(
+            Pawn::new(Color::Red)
+                .moves(Square::E0, BitBoard::EMPTY)
+                .popcnt()
+                == 2
+        );
+    }
+}
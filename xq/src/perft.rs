@@ -0,0 +1,119 @@
+use types::{Move, Position};
+
+use crate::lookup::Lookup;
+
+// Number of leaf nodes of the legal move tree `depth` plies deep.
+pub fn perft(lookup: &Lookup, position: &Position, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = lookup.legal_moves(position);
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    moves
+        .into_iter()
+        .map(|mv| {
+            let mut child = position.clone();
+            child.play_unchecked(mv);
+            perft(lookup, &child, depth - 1)
+        })
+        .sum()
+}
+
+// `perft` of the position after each root move, for finding which move
+// a wrong total comes from.
+pub fn divide(lookup: &Lookup, position: &Position, depth: u32) -> Vec<(Move, u64)> {
+    if depth == 0 {
+        return Vec::new();
+    }
+    lookup
+        .legal_moves(position)
+        .into_iter()
+        .map(|mv| {
+            let mut child = position.clone();
+            child.play_unchecked(mv);
+            (mv, perft(lookup, &child, depth - 1))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::movegen::lookup;
+
+    // Published counts for depths 1, 2, 3, ...
+    const POSITIONS: [(&str, &[u64]); 12] = [
+        (Position::START_FEN, &[44, 1920, 79666]),
+        (
+            "1rbaka2R/5r3/6n2/2p1p1p2/4P1bP1/PpC3Bc1/1nPR2P2/2N2AN2/1c2K1p2/2BAC4 w - - 0 1",
+            &[49, 2265, 100326],
+        ),
+        (
+            "4kcP1N/8n/3rb4/9/9/9/9/3p1A3/4K4/5CB2 w - - 0 1",
+            &[13, 272, 3707, 92741],
+        ),
+        (
+            "r1ba1a3/4kn3/2n1b4/pNp1p1p1p/4c4/6P2/P1P2R2P/1CcC5/9/2BAKAB2 w - - 0 1",
+            &[38, 1128, 43929],
+        ),
+        (
+            "1cbak4/9/n2a5/2p1p3p/5cp2/2n2N3/6PCP/3AB4/2C6/3A1K1N1 w - - 0 1",
+            &[7, 281, 8620],
+        ),
+        (
+            "5a3/3k5/3aR4/9/5r3/5n3/9/3A1A3/5K3/2BC2B2 w - - 0 1",
+            &[25, 424, 9850, 202884],
+        ),
+        (
+            "CRN1k1b2/3ca4/4ba3/9/2nr5/9/9/4B4/4A4/4KA3 w - - 0 1",
+            &[28, 516, 14808],
+        ),
+        (
+            "R1N1k1b2/9/3aba3/9/2nr5/2B6/9/4B4/4A4/4KA3 w - - 0 1",
+            &[21, 364, 7626, 162837],
+        ),
+        (
+            "C1nNk4/9/9/9/9/9/n1pp5/B3C4/9/3A1K3 w - - 0 1",
+            &[28, 222, 6241, 64971],
+        ),
+        (
+            "4ka3/4a4/9/9/4N4/p8/9/4C3c/7n1/2BK5 w - - 0 1",
+            &[23, 345, 8124, 149272],
+        ),
+        (
+            "2b1ka3/9/b3N4/4n4/9/9/9/4C4/2p6/2BK5 w - - 0 1",
+            &[21, 195, 3883, 48060],
+        ),
+        (
+            "CnN1k1b2/c3a4/4ba3/9/2nr5/9/9/4C4/4A4/4KA3 w - - 0 1",
+            &[19, 583, 11714],
+        ),
+    ];
+
+    #[test]
+    fn test_perft() {
+        for (fen, counts) in POSITIONS {
+            let position = Position::from_fen(fen).unwrap();
+            for (depth, &count) in (1..).zip(counts) {
+                let nodes = perft(lookup(), &position, depth);
+                assert!(
+                    nodes == count,
+                    "fail with fen = {}, depth = {}, nodes = {}!",
+                    fen,
+                    depth,
+                    nodes
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_divide() {
+        let position = Position::start();
+        let divided = divide(lookup(), &position, 2);
+        assert!(divided.len() == 44);
+        assert!(divided.iter().map(|(_, nodes)| nodes).sum::<u64>() == 1920);
+    }
+}
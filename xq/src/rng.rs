@@ -1,15 +1,25 @@
 // Simple Pcg64Mcg implementation
+#[derive(Clone)]
 pub struct Rng(u128);
 
+const MULTIPLIER: u128 = 0x2360ED051FC65DA44385DF649FCCF645;
+const DEFAULT_STATE: u128 = 0xE926E6210D9E3486;
+
 impl Default for Rng {
     fn default() -> Self {
-        Self(0xE926E6210D9E3486 | 1)
+        Self::new(0)
     }
 }
 
 impl Rng {
+    // The state must stay odd, so only the low 127 bits of `seed` count: seeds
+    // below 2^127 give distinct states. Seed 0 is the historical default.
+    pub fn new(seed: u128) -> Self {
+        Self(((seed << 1) | 1) ^ DEFAULT_STATE)
+    }
+
     pub fn next_u64(&mut self) -> u64 {
-        self.0 = self.0.wrapping_mul(0x2360ED051FC65DA44385DF649FCCF645);
+        self.0 = self.0.wrapping_mul(MULTIPLIER);
         let rot = (self.0 >> 122) as u32;
         let xsl = (self.0 >> 64) as u64 ^ self.0 as u64;
         xsl.rotate_right(rot)
@@ -19,4 +29,49 @@ impl Rng {
         let y = self.next_u64() as u128;
         (x << 64) | y
     }
+
+    // Jumps `delta` steps ahead in O(log delta) by raising the multiplier to `delta`.
+    pub fn advance(&mut self, mut delta: u128) {
+        let mut multiplier = MULTIPLIER;
+        let mut jump = 1u128;
+        while delta > 0 {
+            if delta & 1 == 1 {
+                jump = jump.wrapping_mul(multiplier);
+            }
+            multiplier = multiplier.wrapping_mul(multiplier);
+            delta >>= 1;
+        }
+        self.0 = self.0.wrapping_mul(jump);
+    }
+
+    // Independent stream `id`, starting 2^64 steps after stream `id - 1`.
+    pub fn stream(&self, id: u64) -> Rng {
+        let mut rng = self.clone();
+        rng.advance((id as u128) << 64);
+        rng
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rng_advance() {
+        let mut stepped = Rng::new(42);
+        for _ in 0..1000 {
+            stepped.next_u64();
+        }
+        let mut jumped = Rng::new(42);
+        jumped.advance(1000);
+        assert!(stepped.next_u128() == jumped.next_u128());
+    }
+
+    #[test]
+    fn test_rng_default_seed() {
+        assert!(Rng::default().0 == DEFAULT_STATE | 1);
+        assert!(Rng::new(1).0 != Rng::new(0).0);
+        // The top bit of the seed is dropped.
+        assert!(Rng::new(1 << 127).0 == Rng::new(0).0);
+    }
 }
@@ -0,0 +1,124 @@
+use types::{BitBoard, Square};
+
+use crate::{
+    cannon::ray_blockers,
+    generate::ChessMove,
+    rook::{Slider, SLIDER_ONE_STEP},
+};
+
+// Squares a rook attacks through its first blocker: those past the blocker,
+// up to and including the next one. The edge square is the last square of a
+// ray either way, so like for the rook whether it is occupied never matters.
+pub struct RookXray {
+    deltas: [(i8, i8); 4],
+}
+
+impl RookXray {
+    pub fn new() -> Self {
+        RookXray {
+            deltas: SLIDER_ONE_STEP,
+        }
+    }
+}
+
+impl ChessMove for RookXray {
+    fn moves(&self, square: Square, blockers: BitBoard) -> BitBoard {
+        let mut moves = BitBoard::EMPTY;
+        for (df, dr) in self.deltas {
+            let mut screened = false;
+            let mut ray = square;
+            while let Some(shifted) = ray.try_offset(df, dr) {
+                ray = shifted;
+                if screened {
+                    moves |= ray.bitboard();
+                    if blockers.has(ray) {
+                        break;
+                    }
+                } else if blockers.has(ray) {
+                    screened = true;
+                }
+            }
+        }
+        moves
+    }
+
+    fn relevant_blockers(&self, square: Square) -> BitBoard {
+        Slider::new(self.deltas, Vec::new()).relevant_blockers(square)
+    }
+
+    fn possible_squares(&self) -> Vec<Square> {
+        Vec::from(Square::ALL)
+    }
+}
+
+// Captures of a cannon over exactly two screens, i.e. the piece it would
+// attack if one of them left the line.
+pub struct CannonXray {
+    deltas: [(i8, i8); 4],
+}
+
+impl CannonXray {
+    pub fn new() -> Self {
+        CannonXray {
+            deltas: SLIDER_ONE_STEP,
+        }
+    }
+}
+
+impl ChessMove for CannonXray {
+    fn moves(&self, square: Square, blockers: BitBoard) -> BitBoard {
+        let mut moves = BitBoard::EMPTY;
+        for (df, dr) in self.deltas {
+            let mut screens = 0;
+            let mut ray = square;
+            while let Some(shifted) = ray.try_offset(df, dr) {
+                ray = shifted;
+                if blockers.has(ray) {
+                    if screens == 2 {
+                        moves |= ray.bitboard();
+                        break;
+                    }
+                    screens += 1;
+                }
+            }
+        }
+        moves
+    }
+
+    fn relevant_blockers(&self, square: Square) -> BitBoard {
+        ray_blockers(&self.deltas, square, 3)
+    }
+
+    fn possible_squares(&self) -> Vec<Square> {
+        Vec::from(Square::ALL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xray() {
+        let blockers = Square::E3.bitboard()
+            | Square::E5.bitboard()
+            | Square::E7.bitboard()
+            | Square::C1.bitboard();
+        let rook = RookXray::new().moves(Square::E1, blockers);
+        assert!(
+            rook == Square::E4.bitboard()
+                | Square::E5.bitboard()
+                | Square::B1.bitboard()
+                | Square::A1.bitboard()
+        );
+        let cannon = CannonXray::new().moves(Square::E1, blockers);
+        assert!(cannon == Square::E7.bitboard());
+        // Nothing past the two screens.
+        let cannon = CannonXray::new().moves(Square::E1, blockers & !Square::E7.bitboard());
+        assert!(cannon.is_empty());
+        // A piece on the edge is captured like any other.
+        let edge = Square::B1.bitboard() | Square::A1.bitboard();
+        let cannon = CannonXray::new().moves(Square::E1, blockers | edge);
+        assert!(cannon == Square::E7.bitboard() | Square::A1.bitboard());
+    }
+}